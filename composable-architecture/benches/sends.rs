@@ -2,6 +2,7 @@
 //!
 //! These benches compare:
 //! - Many external `Store::send` calls
+//! - Many external `Store::send` calls, from several threads at once
 //! - Internal effects that enqueue many actions (`send.action`)
 //! - Stream-based and future-based effect emission
 //!
@@ -59,6 +60,9 @@ impl From<State> for usize {
 /// Large enough to amortise overhead and highlight per-send costs.
 const N: usize = 100000;
 
+/// The number of threads sending at once, in the concurrent benches.
+const THREADS: usize = 4;
+
 mod one_hundred_thousand {
     #[allow(unused_imports)]
     use super::*;
@@ -70,7 +74,24 @@ mod one_hundred_thousand {
             store.send(std::hint::black_box(Action::A));
         }
 
-        let n = store.into_inner();
+        let n = store.into_inner().unwrap();
+        assert_eq!(n, N);
+    }
+
+    #[benchmark(min_time = 1)]
+    fn concurrent_external_sends() {
+        let store = Store::with_initial(State(0));
+        std::thread::scope(|scope| {
+            for _ in 0..THREADS {
+                scope.spawn(|| {
+                    for _ in 0..N / THREADS {
+                        store.send(std::hint::black_box(Action::A));
+                    }
+                });
+            }
+        });
+
+        let n = store.into_inner().unwrap();
         assert_eq!(n, N);
     }
 
@@ -79,7 +100,7 @@ mod one_hundred_thousand {
         let store = Store::with_initial(State(0));
         store.send(std::hint::black_box(Action::B));
 
-        let n = store.into_inner();
+        let n = store.into_inner().unwrap();
         assert_eq!(n, N);
     }
 
@@ -88,7 +109,7 @@ mod one_hundred_thousand {
         let store = Store::with_initial(State(0));
         store.send(std::hint::black_box(Action::C));
 
-        let n = store.into_inner();
+        let n = store.into_inner().unwrap();
         assert_eq!(n, N);
     }
 
@@ -97,7 +118,7 @@ mod one_hundred_thousand {
         let store = Store::with_initial(State(0));
         store.send(std::hint::black_box(Action::D));
 
-        let n = store.into_inner();
+        let n = store.into_inner().unwrap();
         assert_eq!(n, N);
     }
 }
@@ -0,0 +1,110 @@
+//! Micro-benchmarks for timers.
+//!
+//! These benches compare:
+//! - Scheduling many timers, due in no particular order, then cancelling them before they fire
+//! - Scheduling many timers, due in no particular order, and waiting for them all to fire
+//!
+
+use std::time::Duration;
+
+use divan::{bench as benchmark, main as run_benchmarks};
+
+use composable::{Effects, Reducer, Store, Task};
+
+fn main() {
+    run_benchmarks();
+}
+
+#[derive(Default)]
+struct State {
+    fired: usize,
+    cancelled: bool,
+    tasks: Vec<Task>,
+}
+
+#[derive(Clone, Debug)]
+enum Action {
+    Schedule(u64),
+    ScheduleThenCancel,
+    Cancel,
+    Fired,
+}
+
+impl Reducer for State {
+    type Action = Action;
+    type Output = usize;
+
+    #[inline(never)]
+    fn reduce(&mut self, action: Action, send: impl Effects<Action>) {
+        use Action::*;
+
+        match action {
+            Schedule(spread) => self.schedule(Duration::ZERO, spread, &send),
+            ScheduleThenCancel => {
+                self.schedule(HOUR, 3_600_000_000, &send);
+                // once the timers above have all been added to the reactor
+                send.after(Duration::ZERO, Cancel).detach();
+            }
+            Cancel => {
+                self.tasks.clear();
+                self.cancelled = true;
+            }
+            Fired => self.fired += 1,
+        }
+    }
+}
+
+impl State {
+    /// Schedules `N` timers, due between `from` and `spread` microseconds after it.
+    fn schedule(&mut self, from: Duration, spread: u64, send: &impl Effects<Action>) {
+        for n in 0..std::hint::black_box(N) {
+            // a scattered, but repeatable, order of deadlines
+            let micros = (n as u64).wrapping_mul(2_654_435_761) % spread;
+            self.tasks
+                .push(send.after(from + Duration::from_micros(micros), Action::Fired));
+        }
+    }
+}
+
+impl From<State> for usize {
+    fn from(value: State) -> Self {
+        value.fired
+    }
+}
+
+/// Large enough for the cost of each timer to dominate.
+const N: usize = 10000;
+
+/// Long enough that none of the timers fire during the benchmark.
+const HOUR: Duration = Duration::from_secs(3600);
+
+mod ten_thousand {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[benchmark(min_time = 1)]
+    fn schedule_then_cancel() {
+        let store = Store::with_initial(State::default());
+        store.send(Action::ScheduleThenCancel);
+
+        while !store.with_state(|state| state.cancelled).unwrap() {
+            std::thread::yield_now();
+        }
+
+        let n = store.into_inner().unwrap();
+        assert_eq!(n, 0);
+    }
+
+    #[benchmark(min_time = 1)]
+    fn schedule_and_fire() {
+        let store = Store::with_initial(State::default());
+        store.send(Action::Schedule(1000)); // all due within a millisecond
+
+        while store.with_state(|state| state.fired).unwrap() < N {
+            std::thread::yield_now();
+        }
+
+        let n = store.into_inner().unwrap();
+        assert_eq!(n, N);
+    }
+}
@@ -56,7 +56,8 @@ pub fn is_keyed_state(ty: &Type) -> bool {
 /// - If the parent action can `TryInto<Keyed<K, ChildAction>>`, we attempt to look up the child state by key.
 /// - If the key is present, we run the child reducer and scope its effects back into the parent action type
 ///   using `send.scope_keyed(key)`.
-/// - If the key is absent, the action is ignored (no panic).
+/// - If the key is absent, the action is ignored (no panic); and any tasks the missing child started
+///   through `scope_keyed(key)` are cancelled, so that it stops sending actions.
 ///
 /// Note: this relies on the parent action having exactly one conversion route from `Keyed<K, ChildAction>`,
 /// otherwise `From`/`TryInto` coherence will fail or become ambiguous.
@@ -70,6 +71,8 @@ pub fn keyed_child_reduce(into_state: TokenStream2) -> TokenStream2 {
                     child_action,
                     send.scope_keyed(key),
                 );
+            } else {
+                composable::effects::cancel_keyed(key, &child_action);
             }
         }
     }
@@ -41,6 +41,9 @@
 //!   (typically a dedicated enum variant).
 //! - Child effects should be scoped with [`Effects::scope_keyed`](crate::effects::Effects::scope_keyed),
 //!   which automatically re-wraps child actions back into `Keyed<Key, ChildAction>` for the same key.
+//! - Children should be removed with [`KeyedState::remove`](crate::KeyedState::remove), which
+//!   cancels the tasks they started. An action for a key that no longer exists is ignored, and
+//!   also cancels any tasks still running for that key.
 //!
 //! # Composite Reducers
 //!
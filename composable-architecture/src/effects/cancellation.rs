@@ -0,0 +1,192 @@
+//! Cancellation identifiers.
+//!
+//! Tasks made [`cancellable`](crate::Task::cancellable) are kept by the store, rather than by the
+//! reducer, so that reducer state can remain plain data. Each store installs its own
+//! [`Cancellations`] as a dependency of its runtime.
+//!
+//! Identifiers can be of any `'static` type that implements [`PartialEq`]; identifiers of different
+//! types never match.
+//!
+//! [`TaskScope`](crate::effects::TaskScope)s share the same registry, under identifiers of their
+//! own type.
+
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::mem::take;
+use std::rc::Rc;
+
+use crate::effects::Task;
+
+/// The cancellable tasks of a single store, along with their identifiers.
+#[derive(Clone, Default)]
+pub(crate) struct Cancellations {
+    tasks: Rc<RefCell<Vec<Entry>>>,
+    /// The number of tasks when finished tasks were last swept out.
+    swept: Rc<Cell<usize>>,
+}
+
+/// A task, along with the identifier it was registered under.
+type Entry = (Box<dyn Any>, Task);
+
+impl Cancellations {
+    /// Registers `task` under `id`.
+    pub(crate) fn insert<Id: PartialEq + 'static>(&self, id: Id, task: Task) {
+        let mut tasks = self.tasks.borrow_mut();
+        sweep(&mut tasks, &self.swept, |(_, task)| task);
+
+        tasks.push((Box::new(id), task));
+    }
+
+    /// Returns `true` if any task registered under `id` is still running.
+    pub(crate) fn contains<Id: PartialEq + 'static>(&self, id: &Id) -> bool {
+        self.tasks
+            .borrow()
+            .iter()
+            .any(|(key, task)| matches(key.as_ref(), id) && task.is_running())
+    }
+
+    /// Cancels every task registered under `id`.
+    pub(crate) fn cancel<Id: PartialEq + 'static>(&self, id: &Id) {
+        let mut tasks = self.tasks.borrow_mut();
+        let (cancelled, remaining) = take(&mut *tasks)
+            .into_iter()
+            .partition::<Vec<_>, _>(|(key, _)| matches(key.as_ref(), id));
+
+        *tasks = remaining;
+        drop(tasks); // release the `RefCell` before the tasks are dropped
+
+        // tasks added to a `TaskScope` may be detached, and are only cancelled by aborting them
+        for (_, task) in cancelled {
+            task.abort();
+        }
+    }
+}
+
+/// Sweeps finished tasks out of `tasks` whenever it has doubled in size since the last sweep, so
+/// that they cannot pile up; which costs a constant amount per task, amortised.
+///
+/// `swept` holds the number of tasks left by the last sweep.
+pub(crate) fn sweep<T>(tasks: &mut Vec<T>, swept: &Cell<usize>, task: impl Fn(&T) -> &Task) {
+    if tasks.len() > 64 && tasks.len() > 2 * swept.get() {
+        tasks.retain(|entry| task(entry).is_running());
+        swept.set(tasks.len());
+    }
+}
+
+fn matches<Id: PartialEq + 'static>(key: &dyn Any, id: &Id) -> bool {
+    key.downcast_ref::<Id>() == Some(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::stream::pending;
+    use futures::StreamExt;
+
+    use crate::dependencies::Dependency;
+    use crate::effects::Interval;
+    use crate::{Effects, Reducer, TestClock, TestStore};
+
+    use super::Cancellations;
+
+    #[derive(Clone, Debug, Default, PartialEq)]
+    struct State {
+        n: usize,
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Action {
+        Start(u32),
+        Once(u32),
+        Stop(u32),
+        Leading,
+        Tick,
+    }
+
+    impl Reducer for State {
+        type Action = Action;
+        type Output = Self;
+
+        fn reduce(&mut self, action: Action, send: impl Effects<Action>) {
+            match action {
+                Action::Start(id) => send
+                    .task(futures::stream::iter([Action::Tick]).chain(pending()))
+                    .cancellable(id),
+                Action::Once(id) => send
+                    .task(futures::stream::iter([Action::Tick]))
+                    .cancellable(id),
+                Action::Stop(id) => send.cancel(id),
+                Action::Leading => send.debounce_id(
+                    "leading",
+                    Action::Tick,
+                    Interval::Leading(Duration::from_secs(2)),
+                ),
+                Action::Tick => self.n += 1,
+            }
+        }
+    }
+
+    #[test]
+    fn test_cancel_by_id() {
+        let mut store = TestStore::<State>::default();
+
+        store.send(Action::Start(1), |_| {});
+        store.send(Action::Start(2), |_| {});
+        store.advance(Duration::ZERO);
+        store.recv(Action::Tick, |state| state.n = 1);
+        store.recv(Action::Tick, |state| state.n = 2);
+
+        store.send(Action::Stop(1), |_| {});
+        store.send(Action::Stop(3), |_| {}); // no such task
+        assert_eq!(
+            Dependency::<Cancellations>::get()
+                .unwrap()
+                .tasks
+                .borrow()
+                .len(),
+            1
+        );
+
+        store.send(Action::Stop(2), |_| {});
+        assert!(Dependency::<Cancellations>::get()
+            .unwrap()
+            .tasks
+            .borrow()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_finished_tasks_are_swept_out() {
+        let mut store = TestStore::<State>::default();
+
+        for n in 1..=1000 {
+            store.send(Action::Once(n), |_| {});
+            store.advance(Duration::ZERO);
+            store.recv(Action::Tick, |state| state.n = n as usize);
+        }
+
+        let tasks = Dependency::<Cancellations>::get()
+            .unwrap()
+            .tasks
+            .borrow()
+            .len();
+        assert!(tasks <= 65, "{tasks} tasks are still registered");
+    }
+
+    #[test]
+    fn test_leading_debounce_id() {
+        let mut store = TestStore::<State>::default();
+
+        store.send(Action::Leading, |_| {});
+        store.advance(Duration::ZERO);
+        store.recv(Action::Tick, |state| state.n = 1);
+
+        store.send(Action::Leading, |_| {}); // dropped
+        store.advance(Duration::from_secs(3));
+
+        store.send(Action::Leading, |_| {});
+        store.advance(Duration::ZERO);
+        store.recv(Action::Tick, |state| state.n = 2);
+    }
+}
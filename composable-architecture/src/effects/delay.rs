@@ -4,27 +4,34 @@
 //! It is powered by a [`Reactor`](crate::effects::scheduler::Reactor) dependency.
 //!
 //! `Delay` implements both `Future<Output = ()>` and `Stream<Item = ()>`; it yields exactly once.
+//!
+//! A delay until a wall-clock (`SystemTime`) time is woken by the reactor at regular instants, and
+//! checks the reactor’s wall-clock each time, so that it follows any changes to the system clock.
 
 use std::future::Future;
 use std::pin::Pin;
-use std::sync::{Arc, Mutex};
-use std::task::{Context, Poll, Waker};
-use std::time::Instant;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant, SystemTime};
 
 use futures::Stream;
 
 use crate::dependencies::Dependency;
-use crate::effects::scheduler::Reactor;
+use crate::effects::scheduler::{Reactor, Timer};
 
-pub(crate) enum State {
-    New(Instant),
-    Waiting(Waker),
+enum State {
+    /// The instant to wait until, and the reactor’s shift when the delay was created.
+    New(Instant, Duration),
+    Waiting(Timer),
     Ready,
     Done,
 }
 
 /// A one-shot delay that becomes ready once.
-pub struct Delay(Arc<Mutex<State>>);
+pub struct Delay {
+    state: State,
+    /// The wall-clock time to wait until, if the delay is not measured in `Instant`s.
+    until: Option<SystemTime>,
+}
 
 impl Future for Delay {
     type Output = ();
@@ -39,32 +46,37 @@ impl Stream for Delay {
     type Item = ();
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        let mut state = self
-            .0
-            .lock() //
-            .unwrap_or_else(|err| err.into_inner());
-
-        match &mut *state {
-            State::New(instant) => {
-                let instant = *instant;
-                *state = State::Waiting(cx.waker().clone());
-                drop(state);
-
-                // Now that it has a Waker…
-                let scheduler = Dependency::<Reactor>::get();
-                scheduler.add(instant, self.0.clone());
-
-                Poll::Pending
-            }
-            State::Waiting(waker) => {
-                waker.clone_from(cx.waker()); // update the waker if needed
-                Poll::Pending
-            }
-            State::Ready => {
-                *state = State::Done;
-                Poll::Ready(Some(()))
+        let delay = self.get_mut();
+
+        loop {
+            match &mut delay.state {
+                State::New(instant, shift) => {
+                    // Now that it has a Waker…
+                    let scheduler = Dependency::<Reactor>::get();
+                    delay.state = State::Waiting(scheduler.add(*instant, *shift, cx.waker()));
+
+                    return Poll::Pending;
+                }
+                State::Waiting(timer) => match timer.poll(cx.waker()) {
+                    true => delay.state = State::Ready,
+                    false => return Poll::Pending,
+                },
+                State::Ready => {
+                    if let Some(time) = delay.until {
+                        let scheduler = Dependency::<Reactor>::get();
+
+                        if let Some(instant) = scheduler.until(time) {
+                            let shift = scheduler.shift();
+                            delay.state = State::Waiting(scheduler.add(instant, shift, cx.waker()));
+                            return Poll::Pending;
+                        }
+                    }
+
+                    delay.state = State::Done;
+                    return Poll::Ready(Some(()));
+                }
+                State::Done => return Poll::Ready(None),
             }
-            State::Done => Poll::Ready(None),
         }
     }
 
@@ -76,7 +88,24 @@ impl Stream for Delay {
 
 impl Delay {
     /// Create a delay that becomes ready at `instant` (or the first poll after it).
+    ///
+    /// Time spent with the timers [paused](crate::Store::pause_timers) after the delay is created
+    /// pushes it back.
     pub fn new(instant: Instant) -> Self {
-        Delay(Arc::new(Mutex::new(State::New(instant))))
+        let shift = Dependency::<Reactor>::get().shift();
+
+        Delay {
+            state: State::New(instant, shift),
+            until: None,
+        }
+    }
+
+    /// Create a delay that becomes ready once the wall-clock reaches `time`.
+    pub fn until(time: SystemTime) -> Self {
+        Delay {
+            // `Ready` so that the first poll checks the wall-clock
+            state: State::Ready,
+            until: Some(time),
+        }
     }
 }
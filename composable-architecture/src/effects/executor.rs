@@ -0,0 +1,366 @@
+//! The executors that run a `Store`’s effects.
+//!
+//! A [`Store`](crate::Store) runs its reducer on a thread of its own, and runs the tasks of its
+//! effects on that same thread; so that neither the state, nor the effects, need to be `Send`. An
+//! [`Executor`] is what runs those tasks, along with the `Store`’s runtime loop itself.
+//!
+//! By default this is a [`LocalPool`]. With the `tokio` feature enabled, [`TokioExecutor`] runs
+//! them inside a (current thread) tokio runtime instead; so that effects can use libraries which
+//! expect a tokio reactor for their I/O and timers.
+
+use std::future::Future;
+use std::rc::Rc;
+
+use futures::executor::LocalPool;
+use futures::task::LocalSpawn;
+
+/// A single-threaded executor for a `Store`’s runtime and the tasks of its effects.
+///
+/// An executor is created on the thread that it will run on; so it need not be `Send`.
+pub trait Executor: 'static {
+    /// Returns a handle that spawns tasks onto the executor.
+    ///
+    /// Tasks may be spawned from within [`block_on`][`Executor::block_on`], or between calls to
+    /// the executor’s methods.
+    fn spawner(&self) -> Rc<dyn LocalSpawn>;
+
+    /// Runs `future` to completion on the current thread, running any spawned tasks while it waits.
+    fn block_on<F: Future>(&mut self, future: F) -> F::Output;
+
+    /// Runs spawned tasks until none of them can make progress without waiting.
+    fn run_until_stalled(&mut self);
+
+    /// Runs spawned tasks until all of them have finished.
+    fn run(&mut self);
+}
+
+impl Executor for LocalPool {
+    fn spawner(&self) -> Rc<dyn LocalSpawn> {
+        Rc::new(LocalPool::spawner(self))
+    }
+
+    fn block_on<F: Future>(&mut self, future: F) -> F::Output {
+        self.run_until(future)
+    }
+
+    fn run_until_stalled(&mut self) {
+        LocalPool::run_until_stalled(self)
+    }
+
+    fn run(&mut self) {
+        LocalPool::run(self)
+    }
+}
+
+#[cfg(feature = "tokio")]
+pub use tokio::TokioExecutor;
+
+#[cfg(feature = "tokio")]
+mod tokio {
+    use std::cell::Cell;
+    use std::future::{poll_fn, Future};
+    use std::rc::{Rc, Weak};
+    use std::task::{Poll, Waker};
+
+    use futures::future::{FutureExt, LocalFutureObj};
+    use futures::task::{LocalSpawn, SpawnError};
+    use tokio::runtime::{Builder, Runtime};
+    use tokio::task::{yield_now, LocalSet};
+
+    use super::Executor;
+
+    /// An [`Executor`] that runs a `Store`’s effects inside a current thread [tokio runtime], with
+    /// all of its drivers enabled.
+    ///
+    /// The effects are spawned onto a [`LocalSet`]; so, as with the default executor, they run on
+    /// the `Store`’s own thread, one at a time, and need not be `Send`.
+    ///
+    /// [tokio runtime]: https://docs.rs/tokio/latest/tokio/runtime/index.html
+    pub struct TokioExecutor {
+        local: Rc<LocalSet>,
+        counts: Rc<Counts>,
+        runtime: Runtime,
+    }
+
+    /// Keeps track of the spawned tasks.
+    #[derive(Default)]
+    struct Counts {
+        polls: Cell<u64>,
+        running: Cell<usize>,
+        /// Woken once no tasks are running.
+        idle: Cell<Option<Waker>>,
+    }
+
+    /// Counts a task as running for as long as it is alive.
+    struct Alive(Rc<Counts>);
+
+    impl Drop for Alive {
+        fn drop(&mut self) {
+            let counts = &self.0;
+            counts.running.set(counts.running.get() - 1);
+
+            if counts.running.get() == 0 {
+                if let Some(waker) = counts.idle.take() {
+                    waker.wake();
+                }
+            }
+        }
+    }
+
+    /// Spawns tasks onto a [`TokioExecutor`].
+    ///
+    /// Like a [`LocalSpawner`][`futures::executor::LocalSpawner`], it does not keep the executor’s
+    /// tasks alive; the tasks may well hold on to it.
+    struct Spawner {
+        local: Weak<LocalSet>,
+        counts: Rc<Counts>,
+    }
+
+    impl LocalSpawn for Spawner {
+        fn spawn_local_obj(
+            &self,
+            mut future: LocalFutureObj<'static, ()>,
+        ) -> Result<(), SpawnError> {
+            let local = self.local.upgrade().ok_or_else(SpawnError::shutdown)?;
+
+            let counts = self.counts.clone();
+            counts.running.set(counts.running.get() + 1);
+            let alive = Alive(counts);
+
+            local.spawn_local(poll_fn(move |cx| {
+                let counts = &alive.0;
+                counts.polls.set(counts.polls.get() + 1);
+
+                future.poll_unpin(cx)
+            }));
+
+            Ok(())
+        }
+    }
+
+    impl Default for TokioExecutor {
+        fn default() -> Self {
+            let runtime = Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("tokio runtime");
+
+            Self {
+                local: Rc::new(LocalSet::new()),
+                counts: Default::default(),
+                runtime,
+            }
+        }
+    }
+
+    impl Executor for TokioExecutor {
+        fn spawner(&self) -> Rc<dyn LocalSpawn> {
+            Rc::new(Spawner {
+                local: Rc::downgrade(&self.local),
+                counts: self.counts.clone(),
+            })
+        }
+
+        fn block_on<F: Future>(&mut self, future: F) -> F::Output {
+            self.runtime.block_on(self.local.run_until(future))
+        }
+
+        fn run_until_stalled(&mut self) {
+            // Each `block_on` runs (at least) one round of the tasks that are ready to run; so
+            // they have stalled once a round does not poll any of them.
+            loop {
+                let polls = self.counts.polls.get();
+                self.block_on(yield_now());
+
+                if self.counts.polls.get() == polls {
+                    break;
+                }
+            }
+        }
+
+        fn run(&mut self) {
+            let counts = self.counts.clone();
+
+            self.block_on(poll_fn(move |cx| match counts.running.get() {
+                0 => Poll::Ready(()),
+                _ => {
+                    counts.idle.set(Some(cx.waker().clone()));
+                    Poll::Pending
+                }
+            }))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::time::Duration;
+
+    use futures::future::LocalFutureObj;
+    use futures::task::SpawnError;
+
+    use super::*;
+    use crate::{Effects, Reducer, TestClock, TestStore};
+
+    #[derive(Clone, Debug, Default, PartialEq)]
+    struct State {
+        log: Vec<&'static str>,
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Action {
+        Start,
+        Done,
+    }
+
+    impl Reducer for State {
+        type Action = Action;
+        type Output = Vec<&'static str>;
+
+        fn reduce(&mut self, action: Action, send: impl Effects<Action>) {
+            self.log.push(match action {
+                Action::Start => {
+                    send.future(async { Some(Action::Done) });
+                    "start"
+                }
+                Action::Done => "done",
+            });
+        }
+    }
+
+    impl From<State> for Vec<&'static str> {
+        fn from(value: State) -> Self {
+            value.log
+        }
+    }
+
+    /// A `LocalPool` that counts the tasks spawned onto it.
+    #[derive(Default)]
+    struct Counting {
+        pool: LocalPool,
+        spawned: Rc<Cell<usize>>,
+    }
+
+    struct CountingSpawner {
+        spawner: Rc<dyn LocalSpawn>,
+        spawned: Rc<Cell<usize>>,
+    }
+
+    impl LocalSpawn for CountingSpawner {
+        fn spawn_local_obj(&self, future: LocalFutureObj<'static, ()>) -> Result<(), SpawnError> {
+            self.spawned.set(self.spawned.get() + 1);
+            self.spawner.spawn_local_obj(future)
+        }
+    }
+
+    impl Executor for Counting {
+        fn spawner(&self) -> Rc<dyn LocalSpawn> {
+            Rc::new(CountingSpawner {
+                spawner: Executor::spawner(&self.pool),
+                spawned: self.spawned.clone(),
+            })
+        }
+
+        fn block_on<F: Future>(&mut self, future: F) -> F::Output {
+            self.pool.block_on(future)
+        }
+
+        fn run_until_stalled(&mut self) {
+            self.pool.run_until_stalled()
+        }
+
+        fn run(&mut self) {
+            Executor::run(&mut self.pool)
+        }
+    }
+
+    #[test]
+    fn test_effects_run_on_the_test_stores_executor() {
+        let executor = Counting::default();
+        let spawned = executor.spawned.clone();
+        let mut store = TestStore::with_executor(State::default(), executor);
+
+        store.send(Action::Start, |state| state.log = vec!["start"]);
+        assert_eq!(spawned.get(), 1);
+
+        store.advance(Duration::ZERO);
+        store.recv(Action::Done, |state| state.log = vec!["start", "done"]);
+    }
+
+    #[cfg(feature = "tokio")]
+    mod tokio {
+        use std::time::Duration;
+
+        use futures::executor::block_on;
+        use futures::StreamExt;
+        #[cfg(not(miri))]
+        use ntest_timeout::timeout;
+        use tokio::time::sleep;
+
+        use crate::effects::TokioExecutor;
+        use crate::{Effects, Reducer, Store, TestClock, TestStore};
+
+        #[derive(Clone, Debug, Default, PartialEq)]
+        struct State {
+            slept: bool,
+        }
+
+        #[derive(Clone, Debug, PartialEq)]
+        enum Action {
+            Sleep,
+            Woke,
+        }
+
+        impl Reducer for State {
+            type Action = Action;
+            type Output = Self;
+
+            fn reduce(&mut self, action: Action, send: impl Effects<Action>) {
+                match action {
+                    Action::Sleep => send.future(async {
+                        sleep(Duration::from_millis(10)).await; // panics outside of a tokio runtime
+                        Some(Action::Woke)
+                    }),
+                    Action::Woke => self.slept = true,
+                }
+            }
+        }
+
+        #[test]
+        #[cfg(not(miri))]
+        #[timeout(10000)]
+        fn test_store_effects_run_inside_tokio() {
+            let store = Store::with_executor(State::default, TokioExecutor::default);
+            let slept = store.subscribe(|state| state.slept);
+
+            store.send(Action::Sleep);
+            block_on(slept.filter(|slept| std::future::ready(*slept)).next());
+
+            assert!(store.into_inner().unwrap().slept);
+        }
+
+        #[test]
+        #[cfg(not(miri))]
+        #[timeout(10000)]
+        fn test_test_store_effects_run_inside_tokio() {
+            let mut store = TestStore::with_executor(State::default(), TokioExecutor::default());
+
+            store.send(Action::Sleep, |_| {});
+            store.wait();
+
+            store.recv(Action::Woke, |state| state.slept = true);
+        }
+
+        #[test]
+        fn test_tokio_executor_runs_until_stalled() {
+            use super::{Action, State};
+
+            let mut store = TestStore::with_executor(State::default(), TokioExecutor::default());
+
+            store.send(Action::Start, |state| state.log = vec!["start"]);
+            store.advance(Duration::ZERO);
+            store.recv(Action::Done, |state| state.log = vec!["start", "done"]);
+        }
+    }
+}
@@ -13,21 +13,45 @@ use std::collections::VecDeque;
 use std::iter::from_fn;
 use std::marker::PhantomData as Marker;
 use std::rc::Weak;
-use std::time::{Duration, Instant};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
 
-use futures::stream::{iter, once};
-use futures::{Future, Stream, StreamExt};
+use futures::channel::mpsc::unbounded;
+use futures::future::{pending, ready, select_all, Either};
+use futures::stream::{empty, iter, once, select};
+use futures::{Future, FutureExt, Stream, StreamExt};
 
+use cancellation::Cancellations;
 pub(crate) use delay::Delay;
-pub(crate) use task::Executor;
+pub use executor::Executor;
+#[cfg(feature = "tokio")]
+pub use executor::TokioExecutor;
+pub use retry::{Backoff, RetryPolicy};
+pub use run::Sender;
+use scheduler::Reactor;
+pub use scheduler::Resume;
 #[doc(hidden)]
 pub use task::Task;
+pub use task::{Completion, Join, Priority};
+pub(crate) use task::{Running, Spawner};
+pub use task_scope::{cancel_keyed, TaskScope};
+use ticks::Ticks;
+pub use ticks::{MissedTicks, TickPolicy};
+pub use workers::Workers;
 
+use crate::dependencies::Dependency;
 use crate::Keyed;
 
+pub(crate) mod cancellation;
 mod delay;
+mod executor;
+mod retry;
+mod run;
 pub(crate) mod scheduler;
 mod task;
+pub(crate) mod task_scope;
+mod ticks;
+mod workers;
 
 /// `Effects` are used within `Reducer`s to propagate follow-up `Action`s as side-effects of handling an action.
 ///
@@ -75,6 +99,401 @@ pub trait Effects: Clone + Scheduler<Action = <Self as Effects>::Action> {
         self.task(stream).detach()
     }
 
+    /// An effect that runs a fallible [`Future`][`std::future`] and sends the
+    /// [`Action`][`Self::Action`] built from its result, by `on_ok` or `on_err`, through the
+    /// `Store`’s [`Reducer`][`crate::Reducer`].
+    ///
+    /// ```rust
+    /// # use composable::*;
+    /// # #[derive(Default)]
+    /// # struct State { user: Option<String> }
+    /// # async fn fetch_user() -> Result<String, std::io::Error> { Ok(String::from("Blob")) }
+    /// #[derive(Clone, Debug)]
+    /// enum Action {
+    ///     Fetch,
+    ///     Fetched(String),
+    ///     Failed(String),
+    /// }
+    ///
+    /// impl Reducer for State {
+    ///     type Action = Action;
+    ///     type Output = Self;
+    ///
+    ///     fn reduce(&mut self, action: Action, send: impl Effects<Action>) {
+    ///         match action {
+    ///             Action::Fetch => send.try_future(fetch_user(), Action::Fetched, |err| {
+    ///                 Action::Failed(err.to_string())
+    ///             }),
+    ///             Action::Fetched(user) => self.user = Some(user),
+    ///             Action::Failed(_) => self.user = None,
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    fn try_future<F, T, E, OnOk, OnErr>(&self, future: F, on_ok: OnOk, on_err: OnErr)
+    where
+        F: Future<Output = Result<T, E>> + 'static,
+        OnOk: FnOnce(T) -> <Self as Effects>::Action + 'static,
+        OnErr: FnOnce(E) -> <Self as Effects>::Action + 'static,
+        <Self as Effects>::Action: 'static,
+    {
+        self.future(future.map(|result| Some(result.map_or_else(on_err, on_ok))))
+    }
+
+    /// An effect that runs a [`Stream`](https://docs.rs/futures/latest/futures/stream/index.html)
+    /// of [`Result`]s and sends an [`Action`][`Self::Action`] for each of them, built by `on_ok` or
+    /// `on_err`, through the `Store`’s [`Reducer`][`crate::Reducer`].
+    ///
+    /// An `Err` does not end the stream; use [`take_while`][`StreamExt::take_while`] (or similar)
+    /// if it should.
+    ///
+    /// As with [`task`][`Effects::task`], the returned [`Task`] can be used to cancel the stream.
+    fn try_task<S, T, E, OnOk, OnErr>(&self, stream: S, mut on_ok: OnOk, mut on_err: OnErr) -> Task
+    where
+        S: Stream<Item = Result<T, E>> + 'static,
+        OnOk: FnMut(T) -> <Self as Effects>::Action + 'static,
+        OnErr: FnMut(E) -> <Self as Effects>::Action + 'static,
+    {
+        self.task(stream.map(move |result| match result {
+            Ok(value) => on_ok(value),
+            Err(err) => on_err(err),
+        }))
+    }
+
+    /// An effect that runs a [`Future`][`std::future`] as [`future`][`Effects::future`] does, unless
+    /// it has not completed within `duration`; in which case it is cancelled and `on_timeout` is
+    /// sent instead.
+    ///
+    /// The deadline is measured from [`now`][`Scheduler::now`], so it can be driven by
+    /// [`TestClock::advance`][`crate::TestClock::advance`] in tests. A `duration` too long to
+    /// measure, such as [`Duration::MAX`], never times out.
+    fn timeout<F>(
+        &self,
+        duration: Duration,
+        future: F,
+        on_timeout: <Self as Effects>::Action,
+    ) -> Task
+    where
+        F: Future<Output = Option<<Self as Effects>::Action>> + 'static,
+        <Self as Effects>::Action: 'static,
+    {
+        let Some(deadline) = self.now().checked_add(duration) else {
+            return self.race([future]); // too far in the future to ever happen
+        };
+
+        let deadline = FutureExt::map(Delay::new(deadline), |_| Some(on_timeout));
+        self.race([Either::Left(future), Either::Right(deadline)])
+    }
+
+    /// An effect that runs several [`Future`][`std::future`]s at once. The first to complete wins,
+    /// and its [`Action`][`Self::Action`] (if any) is sent through the `Store`’s
+    /// [`Reducer`][`crate::Reducer`]. The others are cancelled.
+    fn race<I, F>(&self, futures: I) -> Task
+    where
+        I: IntoIterator<Item = F>,
+        F: Future<Output = Option<<Self as Effects>::Action>> + 'static,
+        <Self as Effects>::Action: 'static,
+    {
+        let futures: Vec<_> = futures.into_iter().map(Box::pin).collect();
+        if futures.is_empty() {
+            return self.task(empty()); // `select_all` panics without any futures
+        }
+
+        // the losers are dropped, along with the rest of `select_all`’s output
+        let winner = select_all(futures).map(|(action, _, _)| action);
+        self.task(once(winner).filter_map(ready))
+    }
+
+    /// An effect that sends an [`Action`][`Self::Action`] through the `Store`’s
+    /// [`Reducer`][`crate::Reducer`] once `task` has finished; but not if it is cancelled.
+    ///
+    /// The returned [`Task`] stands in for `task`: cancelling it cancels `task` as well.
+    fn on_complete(&self, task: Task, action: impl Into<<Self as Effects>::Action>) -> Task
+    where
+        <Self as Effects>::Action: 'static,
+    {
+        let action = action.into();
+        let completed = async move { (task.await == Completion::Finished).then_some(action) };
+
+        self.task(once(completed).filter_map(ready))
+    }
+
+    /// An effect that runs an asynchronous closure, which can send any number of
+    /// [`Action`][`Self::Action`]s through the `Store`’s [`Reducer`][`crate::Reducer`] using the
+    /// [`Sender`] it is given.
+    ///
+    /// The returned [`Task`] finishes once the closure’s future has completed and every action it
+    /// sent has been processed. [`detach`][`Task::detach`] it if it does not need to be cancelled.
+    ///
+    /// ```rust
+    /// # use composable::*;
+    /// # #[derive(Default)]
+    /// # struct State { progress: f32 }
+    /// #[derive(Clone, Debug)]
+    /// enum Action {
+    ///     Download,
+    ///     Progress(f32),
+    /// }
+    ///
+    /// impl Reducer for State {
+    ///     type Action = Action;
+    ///     type Output = Self;
+    ///
+    ///     fn reduce(&mut self, action: Action, send: impl Effects<Action>) {
+    ///         match action {
+    ///             Action::Download => send
+    ///                 .run(async move |send| {
+    ///                     for chunk in 1..=4 {
+    ///                         // let bytes = download(chunk).await;
+    ///                         send.action(Action::Progress(chunk as f32 / 4.0));
+    ///                     }
+    ///                 })
+    ///                 .detach(),
+    ///             Action::Progress(progress) => self.progress = progress,
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    fn run<F, Fut>(&self, f: F) -> Task
+    where
+        F: FnOnce(Sender<<Self as Effects>::Action>) -> Fut,
+        Fut: Future<Output = ()> + 'static,
+        <Self as Effects>::Action: 'static,
+    {
+        let (actions, received) = unbounded();
+        let finished = once(f(Sender { actions })).filter_map(|_| ready(None));
+
+        // ends once the future has completed and every `Sender` has been dropped
+        self.task(select(received, finished))
+    }
+
+    /// An effect that runs the blocking, or CPU-heavy, `work` on one of the `Store`’s
+    /// [`Workers`], and sends the [`Action`][`Self::Action`] it returns through the `Store`’s
+    /// [`Reducer`][`crate::Reducer`].
+    ///
+    /// The `Store`’s thread, and with it every other action, carries on while `work` runs. If
+    /// `work` panics, no action is sent. Cancelling the returned [`Task`] before `work` has started
+    /// skips it; once started, it runs to the end, but its action is dropped.
+    ///
+    /// A [`TestStore`][`crate::TestStore`] runs `work` inline, as the task is first polled.
+    ///
+    /// ```rust
+    /// # use composable::*;
+    /// # #[derive(Default)]
+    /// # struct State { digest: u64 }
+    /// # fn digest(path: &str) -> u64 { 0 }
+    /// #[derive(Clone, Debug)]
+    /// enum Action {
+    ///     Hash(&'static str),
+    ///     Hashed(u64),
+    /// }
+    ///
+    /// impl Reducer for State {
+    ///     type Action = Action;
+    ///     type Output = Self;
+    ///
+    ///     fn reduce(&mut self, action: Action, send: impl Effects<Action>) {
+    ///         match action {
+    ///             Action::Hash(path) => send.blocking(move || Action::Hashed(digest(path))).detach(),
+    ///             Action::Hashed(digest) => self.digest = digest,
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    fn blocking<F>(&self, work: F) -> Task
+    where
+        F: FnOnce() -> <Self as Effects>::Action + Send + 'static,
+        <Self as Effects>::Action: Send + 'static,
+    {
+        let result = Dependency::<Workers>::get().run(work);
+        self.task(once(result).filter_map(ready))
+    }
+
+    /// An effect that runs a [`Send`] [`Future`][`std::future`] on one of the `Store`’s
+    /// [`Workers`] and, if it returns an [`Action`][`Self::Action`], sends it through the `Store`’s
+    /// [`Reducer`][`crate::Reducer`].
+    ///
+    /// The future has a worker thread to itself until it completes, so it may block as well as
+    /// await. If it panics, no action is sent. Cancelling the returned [`Task`] stops the future
+    /// the next time it yields.
+    ///
+    /// A [`TestStore`][`crate::TestStore`] runs the future inline, on its own executor.
+    fn spawn_send<F>(&self, future: F) -> Task
+    where
+        F: Future<Output = Option<<Self as Effects>::Action>> + Send + 'static,
+        <Self as Effects>::Action: Send + 'static,
+    {
+        let result = Dependency::<Workers>::get().spawn(future);
+        self.task(once(result).filter_map(|action| ready(action.flatten())))
+    }
+
+    /// An effect that calls `factory` and runs the future it returns, retrying with a new future
+    /// from `factory` whenever it fails, according to `policy`.
+    ///
+    /// The future resolves to the action to send, for success (`Ok`) and failure (`Err`) alike.
+    /// The `Err` action is only sent once the policy’s attempts have run out.
+    ///
+    /// The waits between attempts are measured from [`now`][`Scheduler::now`], so they can be
+    /// driven by [`TestClock::advance`][`crate::TestClock::advance`] in tests.
+    ///
+    /// ```rust
+    /// # use std::time::Duration;
+    /// # use composable::*;
+    /// # #[derive(Default)]
+    /// # struct State { user: Option<String>, retries: u32 }
+    /// # async fn fetch_user() -> Result<String, std::io::Error> { Ok(String::from("Blob")) }
+    /// #[derive(Clone, Debug)]
+    /// enum Action {
+    ///     Fetch,
+    ///     Retrying(u32),
+    ///     Fetched(String),
+    ///     Failed(String),
+    /// }
+    ///
+    /// impl Reducer for State {
+    ///     type Action = Action;
+    ///     type Output = Self;
+    ///
+    ///     fn reduce(&mut self, action: Action, send: impl Effects<Action>) {
+    ///         match action {
+    ///             Action::Fetch => {
+    ///                 let policy = RetryPolicy::new(Backoff::Exponential(Duration::from_secs(1)), 5)
+    ///                     .max_delay(Duration::from_secs(10))
+    ///                     .on_retry(Action::Retrying);
+    ///
+    ///                 send.retry(
+    ///                     || async {
+    ///                         fetch_user()
+    ///                             .await
+    ///                             .map(Action::Fetched)
+    ///                             .map_err(|err| Action::Failed(err.to_string()))
+    ///                     },
+    ///                     policy,
+    ///                 )
+    ///                 .detach()
+    ///             }
+    ///             Action::Retrying(n) => self.retries = n,
+    ///             Action::Fetched(user) => self.user = Some(user),
+    ///             Action::Failed(_) => self.user = None,
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    fn retry<F, Fut>(
+        &self,
+        mut factory: F,
+        mut policy: RetryPolicy<<Self as Effects>::Action>,
+    ) -> Task
+    where
+        Self: 'static,
+        F: FnMut() -> Fut + 'static,
+        Fut:
+            Future<Output = Result<<Self as Effects>::Action, <Self as Effects>::Action>> + 'static,
+        <Self as Effects>::Action: 'static,
+    {
+        let scheduler = self.clone();
+
+        self.run(move |send| async move {
+            let mut attempt = 1;
+
+            loop {
+                match factory().await {
+                    Ok(action) => return send.action(action),
+                    Err(action) if attempt == policy.max_attempts => return send.action(action),
+                    Err(_) => {
+                        if let Some(on_retry) = policy.on_retry.as_mut() {
+                            send.action(on_retry(attempt));
+                        }
+
+                        let random = || Dependency::<Reactor>::get().random();
+                        match scheduler.now().checked_add(policy.delay(attempt, random)) {
+                            Some(instant) => Delay::new(instant).await,
+                            None => pending().await, // too far in the future to ever happen
+                        }
+
+                        attempt += 1;
+                    }
+                }
+            }
+        })
+    }
+
+    /// Cancels every task made [`cancellable`][`Task::cancellable`] with `id`.
+    fn cancel<Id: PartialEq + 'static>(&self, id: Id) {
+        if let Some(cancellations) = Dependency::<Cancellations>::get().as_deref() {
+            cancellations.cancel(&id);
+        }
+    }
+
+    /// Like [`debounce`][`Scheduler::debounce`], but the previous task is kept by the `Store`
+    /// under `id`, rather than in the reducer’s state.
+    ///
+    /// ```rust
+    /// # use std::time::Duration;
+    /// # use composable::*;
+    /// #[derive(Clone, Debug, Default, PartialEq)]
+    /// struct State {
+    ///     query: String,
+    /// }
+    ///
+    /// #[derive(Clone, Debug, PartialEq)]
+    /// enum Action {
+    ///     Typed(String),
+    ///     Search,
+    /// }
+    ///
+    /// #[derive(PartialEq)]
+    /// struct SearchId;
+    ///
+    /// impl Reducer for State {
+    ///     type Action = Action;
+    ///     type Output = Self;
+    ///
+    ///     fn reduce(&mut self, action: Action, send: impl Effects<Action>) {
+    ///         match action {
+    ///             Action::Typed(query) => {
+    ///                 self.query = query;
+    ///                 let interval = Interval::Trailing(Duration::from_millis(300));
+    ///                 send.debounce_id(SearchId, Action::Search, interval);
+    ///             }
+    ///             Action::Search => {}
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    fn debounce_id<Id>(&self, id: Id, action: <Self as Effects>::Action, interval: Interval)
+    where
+        Id: PartialEq + 'static,
+        <Self as Effects>::Action: Clone + 'static,
+    {
+        let cancellations = Dependency::<Cancellations>::get();
+        let Some(cancellations) = cancellations.as_deref() else {
+            return;
+        };
+
+        let task = match interval {
+            Interval::Trailing(timeout) => {
+                cancellations.cancel(&id);
+                self.after(timeout, action)
+            }
+            Interval::Leading(timeout) => {
+                if cancellations.contains(&id) {
+                    return; // A leading debounce DROPS subsequent actions within the interval
+                }
+
+                // The task keeps running, silently, until the interval has passed.
+                let now = self.now();
+                let mut action = Some(action);
+                let sent = once(Delay::new(now)).map(move |_| action.take());
+                let quiet = once(Delay::new(now + timeout)).map(|_| None);
+
+                self.task(sent.chain(quiet).filter_map(ready))
+            }
+        };
+
+        cancellations.insert(id, task);
+    }
+
     /// Scopes the `Effects` down to one that sends child actions.
     ///
     /// For example, the inner loop of the [`RecursiveReducer`] macro is,
@@ -110,11 +529,15 @@ pub trait Effects: Clone + Scheduler<Action = <Self as Effects>::Action> {
     ///
     /// - `Keyed<TabsKey, ChildAction>`
     /// - `Keyed<JobsKey, ChildAction>`
+    ///
+    /// # Tasks
+    /// Tasks started through the returned `Effects` are added to the [`TaskScope`] of `key`, and
+    /// are cancelled when the child is removed with [`KeyedState::remove`][`crate::KeyedState::remove`].
     #[inline(always)]
     fn scope_keyed<K, ChildAction>(&self, key: K) -> ScopedKeyed<Self, K, ChildAction>
     where
         <Self as Effects>::Action: From<Keyed<K, ChildAction>>,
-        K: Clone + 'static,
+        K: Clone + PartialEq + 'static,
         ChildAction: 'static,
     {
         ScopedKeyed(self.clone(), key, Marker)
@@ -159,26 +582,73 @@ pub trait Scheduler {
         task
     }
 
+    /// Returns the current wall-clock time.
+    ///
+    /// Prefer this to [`SystemTime::now`], as a [`TestStore`][`crate::TestStore`] simulates it.
+    fn system_time(&self) -> SystemTime {
+        Dependency::<Reactor>::get().system_time()
+    }
+
+    /// Sends the `Action` at `time`, as measured by the system’s wall-clock.
+    ///
+    /// Unlike [`at`][`Scheduler::at`], this follows changes to the system clock; the `Action` is
+    /// sent within a second of the clock reaching `time`, even if the clock jumps forwards (or
+    /// backwards) in the meantime.
+    fn at_system_time(&self, time: SystemTime, action: Self::Action) -> Task
+    where
+        Self::Action: Clone + 'static,
+    {
+        self.schedule(action, [Delay::until(time)])
+    }
+
     /// Sends the `Action` every `interval`.
+    ///
+    /// Overdue ticks are sent back to back, to catch up; use [`every_with`][`Scheduler::every_with`]
+    /// to choose otherwise.
+    ///
+    /// While the `Store`’s timers are [paused](crate::Store::pause_timers) no ticks are sent, and
+    /// none of the missed ticks are sent afterwards; the interval carries on from where it was,
+    /// later by the time spent paused.
     fn every(&self, interval: Interval, action: Self::Action) -> Task
     where
         Self::Action: Clone + 'static,
     {
-        let (mut n, duration) = match interval {
+        self.every_with(interval, TickPolicy::default(), action)
+    }
+
+    /// Sends the `Action` every `interval`, handling overdue ticks according to `policy`.
+    ///
+    /// The returned [`Task`]’s [`next_fire`][`Task::next_fire`] is when the next tick is due.
+    fn every_with(&self, interval: Interval, policy: TickPolicy, action: Self::Action) -> Task
+    where
+        Self::Action: Clone + 'static,
+    {
+        let (n, duration) = match interval {
             Interval::Leading(duration) => (0, duration), // 0 × delay => no initial delay
             Interval::Trailing(duration) => (1, duration),
         };
 
-        let start = self.now();
-        self.schedule(
+        // time spent with the timers paused pushes back every later tick
+        let shift = Dependency::<Reactor>::get().shift();
+
+        let mut ticks = Ticks::new(policy, self.now(), duration, n);
+        let next = Arc::new(Mutex::new(None));
+        let shared = next.clone();
+
+        let mut task = self.schedule(
             action,
             from_fn(move || {
-                let instant = start.checked_add(duration.checked_mul(n)?)?;
-                n = n.checked_add(1)?;
+                let reactor = Dependency::<Reactor>::get();
+                let paused = reactor.shift() - shift;
+                let instant = ticks.next(reactor.now(), paused, || reactor.random())?;
 
+                *shared.lock().unwrap() = Some(instant);
                 Some(Delay::new(instant))
             }),
-        )
+        );
+
+        task.next = Some(next);
+        task
     }
 
     /// An effect that coalesces repeated attempts to send [`Action`][`Effects::Action`]s
@@ -305,6 +775,11 @@ where
 {
     type Action = Child;
 
+    #[inline(always)]
+    fn now(&self) -> Instant {
+        self.0.now()
+    }
+
     #[inline(always)]
     fn schedule(
         &self,
@@ -338,7 +813,7 @@ impl<Parent, K, Child> Effects for ScopedKeyed<Parent, K, Child>
 where
     Parent: Effects,
     <Parent as Effects>::Action: Clone + From<Keyed<K, Child>> + 'static,
-    K: Clone + 'static,
+    K: Clone + PartialEq + 'static,
     Child: 'static,
 {
     type Action = Child;
@@ -351,8 +826,11 @@ where
     #[inline(always)]
     fn task<S: Stream<Item = Child> + 'static>(&self, stream: S) -> Task {
         let key = self.1.clone();
-        self.0
-            .task(stream.map(move |action| Keyed::new(key.clone(), action).into()))
+        let task = self
+            .0
+            .task(stream.map(move |action| Keyed::new(key.clone(), action).into()));
+
+        task_scope::keyed::<K, Child>(self.1.clone()).add(task)
     }
 }
 
@@ -361,11 +839,16 @@ impl<Parent, K, Child> Scheduler for ScopedKeyed<Parent, K, Child>
 where
     Parent: Effects,
     <Parent as Effects>::Action: From<Keyed<K, Child>> + Clone + 'static,
-    K: Clone + 'static,
+    K: Clone + PartialEq + 'static,
     Child: 'static,
 {
     type Action = Child;
 
+    #[inline(always)]
+    fn now(&self) -> Instant {
+        self.0.now()
+    }
+
     #[inline(always)]
     fn schedule(
         &self,
@@ -375,8 +858,11 @@ where
     where
         Self::Action: Clone + 'static,
     {
-        self.0
-            .schedule(Keyed::new(self.1.clone(), action).into(), after)
+        let task = self
+            .0
+            .schedule(Keyed::new(self.1.clone(), action).into(), after);
+
+        task_scope::keyed::<K, Child>(self.1.clone()).add(task)
     }
 }
 
@@ -414,3 +900,278 @@ impl<Action: 'static> Scheduler for Weak<RefCell<VecDeque<Action>>> {
         }))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::stream::iter;
+
+    use crate::{Effects, Reducer, TestClock, TestStore};
+
+    #[derive(Clone, Debug, Default, PartialEq)]
+    struct State {
+        total: u32,
+        errors: Vec<String>,
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Action {
+        Parse(&'static str),
+        ParseAll(Vec<&'static str>),
+        Parsed(u32),
+        Failed(String),
+    }
+
+    impl Reducer for State {
+        type Action = Action;
+        type Output = Self;
+
+        fn reduce(&mut self, action: Action, send: impl Effects<Action>) {
+            let failed = |err: std::num::ParseIntError| Action::Failed(err.to_string());
+
+            match action {
+                Action::Parse(text) => {
+                    send.try_future(async move { text.parse() }, Action::Parsed, failed)
+                }
+                Action::ParseAll(texts) => send
+                    .try_task(
+                        iter(texts.into_iter().map(str::parse)),
+                        Action::Parsed,
+                        failed,
+                    )
+                    .detach(),
+                Action::Parsed(n) => self.total += n,
+                Action::Failed(err) => self.errors.push(err),
+            }
+        }
+    }
+
+    #[test]
+    fn test_try_future() {
+        let mut store = TestStore::<State>::default();
+
+        store.send(Action::Parse("1"), |_| {});
+        store.advance(Duration::ZERO);
+        store.recv(Action::Parsed(1), |state| state.total = 1);
+
+        store.send(Action::Parse("one"), |_| {});
+        store.advance(Duration::ZERO);
+
+        let err = String::from("invalid digit found in string");
+        store.recv(Action::Failed(err.clone()), |state| {
+            state.errors = vec![err]
+        });
+    }
+
+    #[test]
+    fn test_try_task_continues_after_errors() {
+        let mut store = TestStore::<State>::default();
+
+        store.send(Action::ParseAll(vec!["1", "", "2"]), |_| {});
+        store.advance(Duration::ZERO);
+
+        let err = String::from("cannot parse integer from empty string");
+        store.recv(Action::Parsed(1), |state| state.total = 1);
+        store.recv(Action::Failed(err.clone()), |state| {
+            state.errors = vec![err]
+        });
+        store.recv(Action::Parsed(2), |state| state.total = 3);
+    }
+
+    mod races {
+        use std::rc::Rc;
+
+        use futures::future::pending;
+        use futures::FutureExt;
+
+        use super::*;
+        use crate::effects::Delay;
+
+        #[derive(Clone, Debug, Default)]
+        struct State {
+            loser: Rc<()>, // held by the losing future
+            result: Option<&'static str>,
+        }
+
+        impl PartialEq for State {
+            fn eq(&self, other: &Self) -> bool {
+                self.result == other.result
+            }
+        }
+
+        #[derive(Clone, Debug, PartialEq)]
+        enum Action {
+            Wait(u64),
+            WaitForever,
+            Race,
+            Finished(&'static str),
+        }
+
+        impl Reducer for State {
+            type Action = Action;
+            type Output = Self;
+
+            fn reduce(&mut self, action: Action, send: impl Effects<Action>) {
+                let after = |secs, name| {
+                    let delay = Delay::new(send.now() + Duration::from_secs(secs));
+                    async move {
+                        delay.await;
+                        Some(Action::Finished(name))
+                    }
+                };
+
+                match action {
+                    Action::Wait(secs) => send
+                        .timeout(
+                            Duration::from_secs(2),
+                            after(secs, "done"),
+                            Action::Finished("timeout"),
+                        )
+                        .detach(),
+                    Action::WaitForever => send
+                        .timeout(Duration::MAX, after(1, "done"), Action::Finished("timeout"))
+                        .detach(),
+                    Action::Race => {
+                        let loser = self.loser.clone();
+                        let forever = async move {
+                            let _loser = loser;
+                            pending::<()>().await;
+                            None
+                        };
+
+                        send.race([
+                            forever.boxed_local(),
+                            after(1, "first").boxed_local(),
+                            after(2, "second").boxed_local(),
+                        ])
+                        .detach()
+                    }
+                    Action::Finished(name) => self.result = Some(name),
+                }
+            }
+        }
+
+        #[test]
+        fn test_timeout() {
+            let mut store = TestStore::<State>::default();
+
+            store.send(Action::Wait(1), |_| {});
+            store.advance(Duration::from_secs(3));
+            store.recv(Action::Finished("done"), |state| {
+                state.result = Some("done")
+            });
+
+            store.send(Action::Wait(3), |_| {});
+            store.advance(Duration::from_secs(1));
+            store.advance(Duration::from_secs(1));
+            store.recv(Action::Finished("timeout"), |state| {
+                state.result = Some("timeout")
+            });
+
+            store.advance(Duration::from_secs(5)); // the future was cancelled
+        }
+
+        #[test]
+        fn test_timeout_without_a_deadline() {
+            let mut store = TestStore::<State>::default();
+
+            store.send(Action::WaitForever, |_| {});
+            store.advance(Duration::from_secs(1));
+            store.recv(Action::Finished("done"), |state| {
+                state.result = Some("done")
+            });
+        }
+
+        #[test]
+        fn test_race_cancels_the_losers() {
+            let state = State::default();
+            let loser = Rc::downgrade(&state.loser);
+            let mut store = TestStore::with_initial(state);
+
+            store.send(Action::Race, |_| {});
+            store.advance(Duration::ZERO);
+            assert_eq!(loser.strong_count(), 2);
+
+            store.advance(Duration::from_secs(5));
+            store.recv(Action::Finished("first"), |state| {
+                state.result = Some("first")
+            });
+            assert_eq!(loser.strong_count(), 1);
+        }
+    }
+
+    mod system_time {
+        use std::time::SystemTime;
+
+        use super::*;
+        use crate::dependencies::Dependency;
+        use crate::effects::scheduler::Reactor;
+
+        #[derive(Clone, Debug, Default, PartialEq)]
+        struct State {
+            reminded: bool,
+        }
+
+        #[derive(Clone, Debug, PartialEq)]
+        enum Action {
+            RemindIn(u64),
+            Remind,
+        }
+
+        impl Reducer for State {
+            type Action = Action;
+            type Output = Self;
+
+            fn reduce(&mut self, action: Action, send: impl Effects<Action>) {
+                match action {
+                    Action::RemindIn(secs) => {
+                        let time = send.system_time() + Duration::from_secs(secs);
+                        send.at_system_time(time, Action::Remind).detach();
+                    }
+                    Action::Remind => self.reminded = true,
+                }
+            }
+        }
+
+        fn system_time() -> SystemTime {
+            Dependency::<Reactor>::get().system_time()
+        }
+
+        #[test]
+        fn test_at_system_time() {
+            let mut store = TestStore::<State>::default();
+
+            store.send(Action::RemindIn(3600), |_| {});
+            store.advance(Duration::from_secs(3599));
+            store.advance(Duration::from_secs(1));
+            store.recv(Action::Remind, |state| state.reminded = true);
+        }
+
+        #[test]
+        fn test_clock_jumps_forwards() {
+            let mut store = TestStore::<State>::default();
+
+            store.send(Action::RemindIn(3600), |_| {});
+            store.advance(Duration::ZERO);
+
+            store.set_system_time(system_time() + Duration::from_secs(7200));
+            store.advance(Duration::from_secs(1));
+            store.recv(Action::Remind, |state| state.reminded = true);
+        }
+
+        #[test]
+        fn test_clock_jumps_backwards() {
+            let mut store = TestStore::<State>::default();
+
+            store.send(Action::RemindIn(10), |_| {});
+            store.advance(Duration::ZERO);
+
+            store.set_system_time(system_time() - Duration::from_secs(3600));
+            store.advance(Duration::from_secs(10));
+            store.advance(Duration::from_secs(3599));
+            store.advance(Duration::from_secs(1));
+            store.recv(Action::Remind, |state| state.reminded = true);
+        }
+    }
+}
@@ -0,0 +1,251 @@
+//! Retry policies for [`Effects::retry`](crate::effects::Effects::retry).
+//!
+//! The waits between attempts are [`Delay`](crate::effects::Delay)s, so they are driven by the
+//! store’s reactor; or, in tests, by [`TestClock::advance`](crate::TestClock::advance).
+
+use std::time::Duration;
+
+/// How long [`Effects::retry`](crate::effects::Effects::retry) waits before each retry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backoff {
+    /// Waits the same `Duration` before every retry.
+    Fixed(Duration),
+    /// Waits the `Duration` before the first retry, and twice as long before each retry after it.
+    Exponential(Duration),
+    /// Waits a random fraction of what [`Exponential`][`Backoff::Exponential`] would wait; so that
+    /// many clients retrying at once do not all retry at the same moment.
+    ///
+    /// The fraction is drawn from the `Store`’s reactor; a [`TestStore`](crate::TestStore) draws
+    /// the same fractions on every run.
+    Jittered(Duration),
+}
+
+impl Backoff {
+    /// Returns the longest wait before the `retry`th retry, counting from one.
+    ///
+    /// A [`Jittered`][`Backoff::Jittered`] backoff waits a random fraction of it.
+    pub fn delay(&self, retry: u32) -> Duration {
+        let exponential = |initial: Duration| {
+            let factor = 2u32.checked_pow(retry.saturating_sub(1));
+            factor
+                .and_then(|factor| initial.checked_mul(factor))
+                .unwrap_or(Duration::MAX)
+        };
+
+        match *self {
+            Backoff::Fixed(duration) => duration,
+            Backoff::Exponential(initial) | Backoff::Jittered(initial) => exponential(initial),
+        }
+    }
+
+    /// Returns the wait before the `retry`th retry; jittered by `random`, in `0.0..1.0`.
+    pub(crate) fn jittered(&self, retry: u32, random: impl FnOnce() -> f64) -> Duration {
+        match self {
+            Backoff::Jittered(_) => self.delay(retry).mul_f64(random()),
+            _ => self.delay(retry),
+        }
+    }
+}
+
+/// When, and how often, [`Effects::retry`](crate::effects::Effects::retry) retries a failed future.
+pub struct RetryPolicy<Action> {
+    pub(crate) backoff: Backoff,
+    pub(crate) max_attempts: u32,
+    pub(crate) max_delay: Duration,
+    pub(crate) on_retry: Option<Box<dyn FnMut(u32) -> Action>>,
+}
+
+impl<Action> RetryPolicy<Action> {
+    /// Makes at most `max_attempts` attempts, including the first, waiting according to `backoff`
+    /// in between.
+    ///
+    /// # Panics
+    /// If `max_attempts` is zero.
+    pub fn new(backoff: Backoff, max_attempts: u32) -> Self {
+        assert!(
+            max_attempts > 0,
+            "a retry policy needs at least one attempt"
+        );
+
+        Self {
+            backoff,
+            max_attempts,
+            max_delay: Duration::MAX,
+            on_retry: None,
+        }
+    }
+
+    /// Never waits longer than `max_delay` between attempts.
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Sends the action returned by `f` before each retry, with the number of that retry; counting
+    /// from one.
+    pub fn on_retry(mut self, f: impl FnMut(u32) -> Action + 'static) -> Self {
+        self.on_retry = Some(Box::new(f));
+        self
+    }
+
+    /// Returns the wait before the `retry`th retry; jittered by `random`, in `0.0..1.0`.
+    pub(crate) fn delay(&self, retry: u32, random: impl FnOnce() -> f64) -> Duration {
+        self.backoff.jittered(retry, random).min(self.max_delay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::effects::scheduler::Reactor;
+    use crate::{Effects, Reducer, TestClock, TestStore};
+
+    #[derive(Clone, Debug, Default)]
+    struct State {
+        failures: Rc<Cell<u32>>, // the number of attempts that will fail
+        jittered: bool,
+        retries: u32,
+        result: Option<Result<u32, u32>>,
+    }
+
+    impl PartialEq for State {
+        fn eq(&self, other: &Self) -> bool {
+            (self.retries, self.result) == (other.retries, other.result)
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Action {
+        Fetch,
+        Retrying(u32),
+        Fetched(u32),
+        Failed(u32),
+        Idle, // fails to `send` while an action is still waiting to be received
+    }
+
+    impl Reducer for State {
+        type Action = Action;
+        type Output = Self;
+
+        fn reduce(&mut self, action: Action, send: impl Effects<Action>) {
+            match action {
+                Action::Fetch => {
+                    let failures = self.failures.clone();
+                    let mut attempt = 0;
+
+                    let backoff = match self.jittered {
+                        false => Backoff::Exponential(Duration::from_secs(1)),
+                        true => Backoff::Jittered(Duration::from_secs(1)),
+                    };
+                    let policy = RetryPolicy::new(backoff, 3).on_retry(Action::Retrying);
+
+                    send.retry(
+                        move || {
+                            attempt += 1;
+                            let failed = attempt <= failures.get();
+
+                            async move {
+                                match failed {
+                                    true => Err(Action::Failed(attempt)),
+                                    false => Ok(Action::Fetched(attempt)),
+                                }
+                            }
+                        },
+                        policy,
+                    )
+                    .detach()
+                }
+                Action::Retrying(n) => self.retries = n,
+                Action::Fetched(n) => self.result = Some(Ok(n)),
+                Action::Failed(n) => self.result = Some(Err(n)),
+                Action::Idle => {}
+            }
+        }
+    }
+
+    fn store(failures: u32) -> TestStore<State> {
+        TestStore::with_initial(State {
+            failures: Rc::new(Cell::new(failures)),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_retry_until_success() {
+        let mut store = store(2);
+
+        store.send(Action::Fetch, |_| {});
+        store.advance(Duration::ZERO);
+        store.recv(Action::Retrying(1), |state| state.retries = 1);
+
+        store.advance(Duration::from_millis(999));
+        store.advance(Duration::from_millis(1));
+        store.recv(Action::Retrying(2), |state| state.retries = 2);
+
+        store.advance(Duration::from_secs(1)); // exponential: 2s before the second retry
+        store.advance(Duration::from_secs(1));
+        store.recv(Action::Fetched(3), |state| state.result = Some(Ok(3)));
+    }
+
+    #[test]
+    fn test_retry_gives_up() {
+        let mut store = store(u32::MAX);
+
+        store.send(Action::Fetch, |_| {});
+        store.advance(Duration::ZERO);
+        store.recv(Action::Retrying(1), |state| state.retries = 1);
+
+        store.advance(Duration::from_secs(1));
+        store.recv(Action::Retrying(2), |state| state.retries = 2);
+
+        store.advance(Duration::from_secs(2));
+        store.recv(Action::Failed(3), |state| state.result = Some(Err(3)));
+    }
+
+    #[test]
+    fn test_jittered_delays_are_reproducible() {
+        // the fractions that the `TestStore`’s reactor will draw, as it has the same seed
+        let reactor = Reactor::new();
+        let first = Duration::from_secs(1).mul_f64(reactor.random());
+        let second = Duration::from_secs(2).mul_f64(reactor.random());
+        let nanosecond = Duration::from_nanos(1);
+
+        let mut store = TestStore::with_initial(State {
+            failures: Rc::new(Cell::new(2)),
+            jittered: true,
+            ..Default::default()
+        });
+
+        store.send(Action::Fetch, |_| {});
+        store.advance(Duration::ZERO);
+        store.recv(Action::Retrying(1), |state| state.retries = 1);
+
+        store.advance(first - nanosecond);
+        store.send(Action::Idle, |_| {});
+        store.advance(nanosecond);
+        store.recv(Action::Retrying(2), |state| state.retries = 2);
+
+        store.advance(second - nanosecond);
+        store.send(Action::Idle, |_| {});
+        store.advance(nanosecond);
+        store.recv(Action::Fetched(3), |state| state.result = Some(Ok(3)));
+    }
+
+    #[test]
+    fn test_backoff_delays() {
+        let second = Duration::from_secs(1);
+
+        assert_eq!(Backoff::Fixed(second).delay(3), second);
+        assert_eq!(Backoff::Exponential(second).delay(1), second);
+        assert_eq!(Backoff::Exponential(second).delay(4), second * 8);
+        assert_eq!(Backoff::Exponential(second).delay(100), Duration::MAX);
+        assert_eq!(Backoff::Jittered(second).delay(4), second * 8);
+        assert_eq!(Backoff::Jittered(second).jittered(4, || 0.25), second * 2);
+
+        let policy = RetryPolicy::<()>::new(Backoff::Exponential(second), 10).max_delay(second * 5);
+        assert_eq!(policy.delay(4, || 1.0), second * 5);
+    }
+}
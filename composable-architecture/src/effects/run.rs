@@ -0,0 +1,142 @@
+//! The handle passed to an [`Effects::run`](crate::effects::Effects::run) closure.
+//!
+//! Actions sent through a [`Sender`] are queued on an unbounded channel that the effect’s task
+//! drains, so the closure can send any number of them, at any point, without having to be written
+//! as a `Stream`.
+
+use futures::channel::mpsc::UnboundedSender;
+
+/// Sends actions from an [`Effects::run`] closure back into the `Store`.
+///
+/// A `Sender` can be cloned, and moved to other threads if its actions are [`Send`]. Its actions
+/// are dropped once the task running the closure has finished or been cancelled.
+///
+/// [`Effects::run`]: crate::effects::Effects::run
+#[derive(Debug)]
+pub struct Sender<Action> {
+    pub(crate) actions: UnboundedSender<Action>,
+}
+
+// Using `#[derive(Clone)]` adds a `Clone` requirement to all `Action`s
+impl<Action> Clone for Sender<Action> {
+    fn clone(&self) -> Self {
+        Sender {
+            actions: self.actions.clone(),
+        }
+    }
+}
+
+impl<Action> Sender<Action> {
+    /// Sends `action` through the `Store`’s [`Reducer`][`crate::Reducer`].
+    #[doc(alias = "send")]
+    pub fn action(&self, action: impl Into<Action>) {
+        self.actions.unbounded_send(action.into()).ok();
+    }
+
+    /// Returns `true` once the task running the closure has been cancelled (or has finished), and
+    /// any further actions will be dropped.
+    ///
+    /// A cancelled task is noticed the next time the `Store`’s executor runs, when it drops the
+    /// closure’s future.
+    ///
+    /// Work done outside of the closure’s own future, on another thread for example, can use this
+    /// to stop early.
+    pub fn is_cancelled(&self) -> bool {
+        self.actions.is_closed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    use futures::future::pending;
+
+    use super::*;
+    use crate::{Effects, Reducer, TestClock, TestStore};
+
+    #[derive(Clone, Debug, Default)]
+    struct State {
+        log: Vec<u32>,
+        sender: Rc<RefCell<Option<Sender<Action>>>>,
+    }
+
+    impl PartialEq for State {
+        fn eq(&self, other: &Self) -> bool {
+            self.log == other.log
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Action {
+        Count(u32),
+        Forever,
+        Stop,
+        Done(u32),
+    }
+
+    impl Reducer for State {
+        type Action = Action;
+        type Output = Self;
+
+        fn reduce(&mut self, action: Action, send: impl Effects<Action>) {
+            match action {
+                Action::Count(n) => send
+                    .run(async move |send| {
+                        for n in 1..=n {
+                            send.action(Action::Done(n));
+                        }
+                    })
+                    .cancellable("count"),
+                Action::Forever => {
+                    let sender = self.sender.clone();
+                    send.run(async move |send| {
+                        *sender.borrow_mut() = Some(send);
+                        pending::<()>().await;
+                    })
+                    .cancellable("forever")
+                }
+                Action::Stop => send.cancel("forever"),
+                Action::Done(n) => self.log.push(n),
+            }
+        }
+    }
+
+    #[test]
+    fn test_run_sends_many_actions() {
+        let mut store = TestStore::<State>::default();
+
+        store.send(Action::Count(3), |_| {});
+        store.advance(Duration::ZERO);
+
+        store.recv(Action::Done(1), |state| state.log = vec![1]);
+        store.recv(Action::Done(2), |state| state.log = vec![1, 2]);
+        store.recv(Action::Done(3), |state| state.log = vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_run_observes_cancellation() {
+        let state = State::default();
+        let sender = state.sender.clone();
+        let mut store = TestStore::with_initial(state);
+
+        store.send(Action::Forever, |_| {});
+        store.advance(Duration::ZERO);
+
+        let send = sender.borrow_mut().take().unwrap();
+        assert!(!send.is_cancelled());
+
+        send.action(Action::Done(1));
+        store.advance(Duration::ZERO);
+        store.recv(Action::Done(1), |state| state.log = vec![1]);
+
+        store.send(Action::Stop, |_| {});
+        store.advance(Duration::ZERO); // the executor drops the cancelled future
+        assert!(send.is_cancelled());
+
+        send.action(Action::Done(2)); // dropped
+        store.advance(Duration::ZERO);
+    }
+}
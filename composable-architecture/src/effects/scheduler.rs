@@ -1,59 +1,137 @@
 //! A minimal time reactor used by [`Delay`](crate::effects::Delay) and scheduling.
 //!
-//! This is intentionally tiny: it maintains an ordered queue of instants and wakes the
-//! futures blocked on them.
+//! This is intentionally tiny: it maintains a heap of timers and wakes the futures blocked on
+//! them.
 //!
 //! - In a live store runtime, the default [`Reactor`] spawns a dedicated thread that parks until
 //!   the next scheduled instant.
 //! - In tests, [`TestStore`](crate::TestStore) installs a reactor created with `Reactor::new()`
 //!   (no thread) and drives it deterministically via `TestClock::advance`.
+//!
+//! Adding a timer takes logarithmic time, and cancelling one (by dropping its [`Timer`]) constant
+//! time: the heap entry of a cancelled timer is left in place, to be skipped once it reaches the
+//! top or swept out as the heap grows.
+//!
+//! The reactor is also the source of the wall-clock (`SystemTime`) used by delays scheduled
+//! against it. A live reactor reads the system clock, while a test reactor keeps a simulated one
+//! that moves along with `TestClock::advance` and can be changed with
+//! `TestStore::set_system_time`.
+//!
+//! The reactor’s timers can also be paused, and later resumed, as a whole. While paused, nothing
+//! in its heap is woken; on resuming the heap is either pushed back by the time spent paused, or
+//! woken all at once (see [`Resume`]).
+//!
+//! Finally, the reactor is the source of the random jitter of retries and repeating intervals. A
+//! test reactor draws it from a fixed seed, so that the jittered delays of a `TestStore` are the
+//! same on every run.
 
 use std::cmp::Reverse;
-use std::collections::VecDeque;
-use std::mem::replace;
-use std::sync::{Arc, Mutex};
+use std::collections::BinaryHeap;
+use std::hash::{BuildHasher, RandomState};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::Waker;
 use std::thread::{park, park_timeout, Builder, JoinHandle};
-use std::time::Instant;
+use std::time::{Duration, Instant, SystemTime};
+
+use futures::task::AtomicWaker;
 
 use crate::dependencies::DependencyDefault;
-use crate::effects::delay::State;
 
 /// Shared between the `Scheduler` and its polling Thread
 #[derive(Default)]
 struct Shared {
-    pub(crate) queue: Queue<Instant, Arc<Mutex<State>>>,
+    timers: Timers,
+    /// When the timers were paused, if they are.
+    paused: Option<Instant>,
+    /// The total time that timers have been pushed back by being paused.
+    shift: Duration,
 }
 
 impl Shared {
-    pub fn poll(now: Instant, shared: &Mutex<Shared>) -> Option<Instant> {
-        let mut shared = shared.lock().unwrap();
-        let delays = shared.queue.drain_until(now);
-        let next = shared.queue.peek_next();
-        drop(shared); // release the `Mutex` in case any of the delayed work wants the `Scheduler`
+    /// Wakes the delays that are due at `now`; returning when the next is due, and whether any
+    /// delays were woken.
+    pub fn poll(now: Instant, shared: &Mutex<Shared>) -> (Option<Instant>, bool) {
+        let mut wakers = Vec::new();
 
-        for delay in delays {
-            let mut state = delay.lock().unwrap();
-            let waiting = replace(&mut *state, State::Ready);
-            drop(state); // release the `Mutex` before the waker is called
+        let mut shared = lock(shared);
+        if shared.paused.is_some() {
+            return (None, false);
+        }
 
-            match waiting {
-                State::Waiting(waker) => waker.wake(),
-                _ => unreachable!(),
-            }
+        let next = shared.timers.expire(now, &mut wakers);
+        drop(shared); // release the `Mutex` in case any of the delayed work wants the `Scheduler`
+
+        let woken = !wakers.is_empty();
+        for waker in wakers {
+            waker.wake();
         }
 
-        next
+        (next, woken)
     }
 }
 
+fn lock(shared: &Mutex<Shared>) -> MutexGuard<'_, Shared> {
+    // the timers are left consistent even if a waker panics, as none is called under the lock
+    shared.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// How a [`Store`](crate::Store)’s timers are resumed after
+/// [`pause_timers`](crate::Store::pause_timers).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Resume {
+    /// Each timer fires after the time it had remaining when the timers were paused, or when it
+    /// was started if that was during the pause; as if no time had passed in between.
+    Preserving,
+    /// Every paused timer fires immediately. Repeating timers then carry on as they would have
+    /// after resuming `Preserving`.
+    Immediately,
+}
+
 /// A minimal [Reactor] that powers the `Delay` future/stream.
 ///
 /// [Reactor]: https://rust-lang.github.io/async-book/08_ecosystem/00_chapter.html#async-runtimes
 pub struct Reactor {
     shared: Arc<Mutex<Shared>>,
     handle: Option<JoinHandle<()>>,
+    /// The simulated clocks of a reactor without a polling thread.
+    simulated: Option<Mutex<Clock>>,
+    random: Mutex<Random>,
+}
+
+#[derive(Clone, Copy)]
+struct Clock {
+    now: Instant,
+    system_time: SystemTime,
+}
+
+/// A small pseudo-random number generator ([SplitMix64]); good enough for jitter.
+///
+/// [SplitMix64]: https://prng.di.unimi.it/splitmix64.c
+struct Random(u64);
+
+impl Random {
+    /// The seed of a test reactor.
+    const SEED: u64 = 0x5EED;
+
+    /// Returns a random fraction in `0.0..1.0`.
+    fn next(&mut self) -> f64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+
+        (z >> 11) as f64 / (1u64 << 53) as f64 // 53 bits
+    }
 }
 
+/// How often a delay scheduled against the wall-clock checks it for changes.
+///
+/// Changes to the system clock are noticed within this time.
+const RECHECK: Duration = Duration::from_secs(1);
+
 impl Default for Reactor {
     #[inline(never)]
     fn default() -> Self {
@@ -64,7 +142,7 @@ impl Default for Reactor {
             .name(std::any::type_name::<Self>().into())
             .spawn(move || loop {
                 let now = Instant::now();
-                let next = Shared::poll(now, &remote);
+                let (next, _) = Shared::poll(now, &remote);
 
                 match next {
                     None => park(),
@@ -76,6 +154,8 @@ impl Default for Reactor {
         Self {
             shared,
             handle: Some(handle),
+            simulated: None,
+            random: Mutex::new(Random(RandomState::new().hash_one(Instant::now()))),
         }
     }
 }
@@ -91,61 +171,511 @@ impl Reactor {
         Self {
             shared,
             handle: None,
+            simulated: Some(Mutex::new(Clock {
+                now: Instant::now(),
+                system_time: SystemTime::now(),
+            })),
+            random: Mutex::new(Random(Random::SEED)),
+        }
+    }
+
+    /// Returns a random fraction in `0.0..1.0`, for jitter.
+    ///
+    /// A reactor without a polling thread always returns the same sequence.
+    pub(crate) fn random(&self) -> f64 {
+        self.random.lock().unwrap().next()
+    }
+
+    /// Returns the reactor’s current instant.
+    pub(crate) fn now(&self) -> Instant {
+        match &self.simulated {
+            None => Instant::now(),
+            Some(clock) => clock.lock().unwrap().now,
+        }
+    }
+
+    /// Returns the reactor’s current wall-clock time.
+    pub(crate) fn system_time(&self) -> SystemTime {
+        match &self.simulated {
+            None => SystemTime::now(),
+            Some(clock) => clock.lock().unwrap().system_time,
+        }
+    }
+
+    /// Moves the simulated clocks forward to `now`.
+    pub(crate) fn advance(&self, now: Instant) {
+        if let Some(clock) = &self.simulated {
+            let mut clock = clock.lock().unwrap();
+            let elapsed = now.saturating_duration_since(clock.now);
+
+            clock.system_time += elapsed;
+            clock.now += elapsed;
+        }
+    }
+
+    /// Changes the simulated wall-clock, as if the system clock had been changed.
+    pub(crate) fn set_system_time(&self, time: SystemTime) {
+        if let Some(clock) = &self.simulated {
+            clock.lock().unwrap().system_time = time;
+        }
+    }
+
+    /// Stops waking delays until [`resume`][`Reactor::resume`] is called.
+    pub(crate) fn pause(&self) {
+        let now = self.now();
+        lock(&self.shared).paused.get_or_insert(now);
+    }
+
+    /// Resumes waking delays after a [`pause`][`Reactor::pause`].
+    ///
+    /// Either way, the [`shift`][`Reactor::shift`] grows by the time spent paused, so that
+    /// repeating timers carry on without catching up on the ticks they missed.
+    pub(crate) fn resume(&self, resume: Resume) {
+        let now = self.now();
+        let mut shared = lock(&self.shared);
+        let Some(paused) = shared.paused.take() else {
+            return;
+        };
+
+        let elapsed = now.saturating_duration_since(paused);
+        shared.shift += elapsed;
+
+        match resume {
+            Resume::Preserving => shared
+                .timers
+                .update_keys(|instant| instant.checked_add(elapsed).unwrap_or(instant)),
+            Resume::Immediately => shared.timers.update_keys(|instant| instant.min(now)),
+        }
+        drop(shared);
+
+        if let Some(handle) = &self.handle {
+            handle.thread().unpark();
         }
     }
 
+    /// Returns the total time that timers have been pushed back by being paused; including the
+    /// time spent so far, if they are paused now.
+    pub(crate) fn shift(&self) -> Duration {
+        let now = self.now();
+        let shared = lock(&self.shared);
+
+        match shared.paused {
+            None => shared.shift,
+            Some(paused) => shared.shift + now.saturating_duration_since(paused),
+        }
+    }
+
+    /// Returns the instant at which a delay until `time` should next check the wall-clock, or
+    /// `None` if `time` has already passed.
+    pub(crate) fn until(&self, time: SystemTime) -> Option<Instant> {
+        let remaining = time.duration_since(self.system_time()).ok()?;
+        if remaining.is_zero() {
+            return None;
+        }
+
+        Some(self.now() + remaining.min(RECHECK))
+    }
+
     /// Polls the reactor at `now`, waking any pending delays that have matured.
-    pub(crate) fn poll(&self, now: Instant) -> Instant {
-        Shared::poll(now, &self.shared).unwrap_or(now)
+    ///
+    /// Returns `true` if any delays were woken.
+    pub(crate) fn poll(&self, now: Instant) -> bool {
+        Shared::poll(now, &self.shared).1
     }
 
     #[inline(never)]
-    /// Adds a delay to be woken at `new`.
-    pub(crate) fn add(&self, new: Instant, state: Arc<Mutex<State>>) {
-        let mut shared = self.shared.lock().unwrap();
-        let next = shared.queue.peek_next();
-        shared.queue.insert(new, state);
+    /// Adds a timer that wakes `waker` at `new`.
+    ///
+    /// `shift` is the reactor’s [`shift`][`Reactor::shift`] when `new` was decided on: the timer is
+    /// pushed back only by the time spent paused since then. Resuming pushes every timer back by
+    /// the whole pause; so a timer added partway through it is keyed as if it had been added when
+    /// the pause began, to only wait out the rest.
+    pub(crate) fn add(&self, new: Instant, shift: Duration, waker: &Waker) -> Timer {
+        let mut shared = lock(&self.shared);
+        let next = shared.timers.peek_next();
+
+        let key = match shared.shift.checked_sub(shift) {
+            Some(later) => new.checked_add(later),
+            None => new.checked_sub(shift - shared.shift),
+        }
+        .unwrap_or(new);
+        let timer = shared.timers.insert(key, waker);
         drop(shared);
 
         match (&self.handle, next) {
             (Some(handle), None) => handle.thread().unpark(), // no `unpark` is scheduled yet
-            (Some(handle), Some(pending)) if new < pending => handle.thread().unpark(),
+            (Some(handle), Some(pending)) if key < pending => handle.thread().unpark(),
             _ => {}
         }
+
+        timer
+    }
+}
+
+/// A timer added to a [`Reactor`]; which is cancelled when dropped.
+///
+/// Neither polling nor cancelling a timer locks the reactor.
+pub(crate) struct Timer(Arc<Signal>);
+
+/// Shared between a [`Timer`] and its entry in the reactor’s heap.
+#[derive(Default)]
+struct Signal {
+    fired: AtomicBool,
+    cancelled: AtomicBool,
+    waker: AtomicWaker,
+}
+
+impl Timer {
+    /// Returns `true` once the timer has fired; otherwise replaces the waker it will wake.
+    pub(crate) fn poll(&self, waker: &Waker) -> bool {
+        if self.0.fired.load(Ordering::Acquire) {
+            return true;
+        }
+
+        self.0.waker.register(waker);
+
+        // the timer may have fired before the waker was registered
+        self.0.fired.load(Ordering::Acquire)
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        self.0.cancelled.store(true, Ordering::Release);
+        self.0.waker.take(); // rather than keeping its task alive until the entry is swept out
+    }
+}
+
+/// A heap entry; ordered by when it is due, then by when it was added.
+struct Entry<Key> {
+    key: Key,
+    sequence: u64,
+    signal: Arc<Signal>,
+}
+
+impl<Key: Ord> Ord for Entry<Key> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (&self.key, self.sequence).cmp(&(&other.key, other.sequence))
+    }
+}
+
+impl<Key: Ord> PartialOrd for Entry<Key> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
     }
 }
 
-pub(crate) struct Queue<Key, Value> {
-    deque: VecDeque<(Reverse<Key>, Value)>,
+impl<Key: Ord> PartialEq for Entry<Key> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl<Key: Ord> Eq for Entry<Key> {}
+
+/// A min-heap of timers.
+pub(crate) struct Timers<Key = Instant> {
+    heap: BinaryHeap<Reverse<Entry<Key>>>,
+    sequence: u64,
+    /// The size of the heap when it was last swept of cancelled timers.
+    swept: usize,
 }
 
 // Using `#[derive(Default)]` adds a `Default` requirement to Key
-impl<Key, Value> Default for Queue<Key, Value> {
+impl<Key: Ord> Default for Timers<Key> {
     fn default() -> Self {
-        Queue {
-            deque: Default::default(),
+        Timers {
+            heap: Default::default(),
+            sequence: 0,
+            swept: 0,
         }
     }
 }
 
-impl<Key: Copy, Value> Queue<Key, Value> {
+impl<Key: Ord + Copy> Timers<Key> {
+    /// Returns when the earliest timer is due; which may be one that has since been cancelled.
     pub fn peek_next(&self) -> Option<Key> {
-        self.deque.back().map(|kv| kv.0 .0)
+        self.heap.peek().map(|entry| entry.0.key)
+    }
+
+    /// Adds a timer that wakes `waker` at `key`.
+    pub fn insert(&mut self, key: Key, waker: &Waker) -> Timer {
+        // sweep out cancelled timers whenever the heap has doubled in size, so that they cannot
+        // pile up; which costs a constant amount per timer, amortised
+        if self.heap.len() > 64 && self.heap.len() > 2 * self.swept {
+            self.heap
+                .retain(|entry| !entry.0.signal.cancelled.load(Ordering::Acquire));
+            self.swept = self.heap.len();
+        }
+
+        let signal = Arc::new(Signal::default());
+        signal.waker.register(waker);
+
+        self.sequence += 1;
+        self.heap.push(Reverse(Entry {
+            key,
+            sequence: self.sequence,
+            signal: signal.clone(),
+        }));
+
+        Timer(signal)
+    }
+
+    /// Fires every timer due at or before `key`, adding their wakers to `wakers`, and returns when
+    /// the next is due.
+    pub fn expire(&mut self, key: Key, wakers: &mut Vec<Waker>) -> Option<Key> {
+        while let Some(Reverse(entry)) = self.heap.peek() {
+            let signal = &entry.signal;
+
+            if !signal.cancelled.load(Ordering::Acquire) {
+                if key < entry.key {
+                    return Some(entry.key);
+                }
+
+                signal.fired.store(true, Ordering::Release);
+                wakers.extend(signal.waker.take());
+            }
+
+            self.heap.pop();
+        }
+
+        None
+    }
+
+    /// Replaces every key with `f(key)`; which must not change their order.
+    pub fn update_keys(&mut self, f: impl Fn(Key) -> Key) {
+        let mut entries = std::mem::take(&mut self.heap).into_vec();
+        for entry in &mut entries {
+            entry.0.key = f(entry.0.key);
+        }
+
+        self.heap = BinaryHeap::from(entries);
     }
 }
 
-impl<Key: PartialOrd, Value> Queue<Key, Value> {
-    pub fn insert(&mut self, key: Key, value: Value) {
-        let key = Reverse(key);
-        let index = self.deque.partition_point(|x| x.0 <= key);
-        self.deque.insert(index, (key, value));
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::effects::Interval;
+    use crate::{Effects, Reducer, TestClock, TestStore};
+
+    #[derive(Clone, Debug, Default, PartialEq)]
+    struct State {
+        ticks: usize,
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Action {
+        After(u64),
+        Every(u64),
+        Idle, // fails to `send` while a tick is still waiting to be received
+        Tick,
+    }
+
+    impl Reducer for State {
+        type Action = Action;
+        type Output = Self;
+
+        fn reduce(&mut self, action: Action, send: impl Effects<Action>) {
+            match action {
+                Action::After(secs) => send.after(Duration::from_secs(secs), Action::Tick).detach(),
+                Action::Every(secs) => send
+                    .every(Interval::Trailing(Duration::from_secs(secs)), Action::Tick)
+                    .detach(),
+                Action::Idle => {}
+                Action::Tick => self.ticks += 1,
+            }
+        }
+    }
+
+    mod timers {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::task::Wake;
+
+        use super::*;
+
+        /// Counts how many times it has been woken.
+        #[derive(Default)]
+        struct Counter(AtomicUsize);
+
+        impl Wake for Counter {
+            fn wake(self: Arc<Self>) {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        fn wake_all(wakers: Vec<Waker>) -> usize {
+            let woken = wakers.len();
+            wakers.into_iter().for_each(Waker::wake);
+            woken
+        }
+
+        #[test]
+        fn test_expire_in_order() {
+            let counter = Arc::new(Counter::default());
+            let waker = Waker::from(counter.clone());
+            let mut timers = Timers::<u32>::default();
+
+            let [last, first, _third, _fourth] =
+                [30, 10, 20, 10].map(|key| timers.insert(key, &waker));
+            assert_eq!(timers.peek_next(), Some(10));
+
+            let mut wakers = Vec::new();
+            assert_eq!(timers.expire(5, &mut wakers), Some(10));
+            assert!(wakers.is_empty());
+
+            assert_eq!(timers.expire(20, &mut wakers), Some(30));
+            assert_eq!(wake_all(wakers), 3);
+            assert_eq!(counter.0.load(Ordering::Relaxed), 3);
+
+            assert!(first.poll(&waker));
+            assert!(!last.poll(&waker));
+        }
+
+        #[test]
+        fn test_cancelled_timers_do_not_fire() {
+            let waker = Waker::from(Arc::new(Counter::default()));
+            let mut timers = Timers::<u32>::default();
+
+            let first = timers.insert(1, &waker);
+            let second = timers.insert(2, &waker);
+            drop(first);
+
+            let mut wakers = Vec::new();
+            assert_eq!(timers.expire(1, &mut wakers), Some(2));
+            assert!(wakers.is_empty());
+
+            assert_eq!(timers.expire(2, &mut wakers), None);
+            assert_eq!(wake_all(wakers), 1);
+            assert!(second.poll(&waker));
+        }
+
+        #[test]
+        fn test_cancelled_timers_are_swept_out() {
+            let waker = Waker::from(Arc::new(Counter::default()));
+            let mut timers = Timers::<u32>::default();
+
+            let live = timers.insert(u32::MAX, &waker);
+            for key in 0..10_000 {
+                drop(timers.insert(key, &waker));
+            }
+
+            assert!(timers.heap.len() <= 2 * 64 + 1);
+
+            let mut wakers = Vec::new();
+            assert_eq!(timers.expire(u32::MAX - 1, &mut wakers), Some(u32::MAX));
+            assert!(wakers.is_empty());
+            assert!(!live.poll(&waker));
+        }
+
+        #[test]
+        fn test_update_keys() {
+            let waker = Waker::from(Arc::new(Counter::default()));
+            let mut timers = Timers::<u32>::default();
+
+            let _timers = [10, 20].map(|key| timers.insert(key, &waker));
+            timers.update_keys(|key| key + 100);
+
+            let mut wakers = Vec::new();
+            assert_eq!(timers.expire(110, &mut wakers), Some(120));
+            assert_eq!(wake_all(wakers), 1);
+        }
+    }
+
+    #[test]
+    fn test_resume_preserving_remaining_time() {
+        let mut store = TestStore::<State>::default();
+
+        store.send(Action::After(3), |_| {});
+        store.advance(Duration::from_secs(1));
+
+        store.pause_timers();
+        store.advance(Duration::from_secs(60));
+        store.send(Action::Idle, |_| {});
+
+        store.resume_timers(Resume::Preserving);
+        store.advance(Duration::from_millis(1999));
+        store.send(Action::Idle, |_| {});
+
+        store.advance(Duration::from_millis(1));
+        store.recv(Action::Tick, |state| state.ticks = 1);
     }
 
-    pub fn drain_until(&mut self, key: Key) -> impl Iterator<Item = Value> {
-        let key = Reverse(key);
-        let index = self.deque.partition_point(|x| x.0 < key);
+    #[test]
+    fn test_resume_preserving_timers_started_while_paused() {
+        let mut store = TestStore::<State>::default();
+
+        store.pause_timers();
+        store.advance(Duration::from_secs(50));
+        store.send(Action::After(1), |_| {});
+        store.advance(Duration::from_secs(10));
+        store.send(Action::Idle, |_| {});
+
+        store.resume_timers(Resume::Preserving);
+        store.advance(Duration::from_millis(999));
+        store.send(Action::Idle, |_| {});
+
+        store.advance(Duration::from_millis(1));
+        store.recv(Action::Tick, |state| state.ticks = 1);
+    }
+
+    #[test]
+    fn test_every_started_while_paused() {
+        let mut store = TestStore::<State>::default();
+
+        store.pause_timers();
+        store.advance(Duration::from_secs(50));
+        store.send(Action::Every(1), |_| {});
+        store.advance(Duration::from_secs(10));
+        store.resume_timers(Resume::Preserving);
+
+        store.advance(Duration::from_secs(1));
+        store.recv(Action::Tick, |state| state.ticks = 1);
+        store.advance(Duration::from_secs(1));
+        store.recv(Action::Tick, |state| state.ticks = 2);
+    }
+
+    #[test]
+    fn test_resume_immediately() {
+        let mut store = TestStore::<State>::default();
+
+        store.send(Action::After(3), |_| {});
+        store.advance(Duration::from_secs(1));
+
+        store.pause_timers();
+        store.send(Action::After(1), |_| {}); // started while paused
+        store.advance(Duration::from_secs(60));
+        store.send(Action::Idle, |_| {});
+
+        store.resume_timers(Resume::Immediately);
+        store.advance(Duration::ZERO);
+        store.recv(Action::Tick, |state| state.ticks = 1);
+        store.recv(Action::Tick, |state| state.ticks = 2);
+    }
+
+    #[test]
+    fn test_every_does_not_catch_up() {
+        let mut store = TestStore::<State>::default();
+
+        store.send(Action::Every(1), |_| {});
+        store.advance(Duration::ZERO);
+        store.advance(Duration::from_secs(1));
+        store.recv(Action::Tick, |state| state.ticks = 1);
+
+        store.pause_timers();
+        store.advance(Duration::from_secs(10));
+        store.resume_timers(Resume::Immediately);
+
+        store.advance(Duration::ZERO);
+        store.recv(Action::Tick, |state| state.ticks = 2);
+        store.send(Action::Idle, |_| {}); // no burst of the ticks missed
 
-        // without the use of `Reverse` for the keys `split_off` would return the wrong half
-        self.deque.split_off(index).into_iter().rev().map(|kv| kv.1)
+        // the next tick is as late as it would have been had the timers been resumed `Preserving`
+        store.advance(Duration::from_secs(1));
+        store.send(Action::Idle, |_| {});
+        store.advance(Duration::from_secs(1));
+        store.recv(Action::Tick, |state| state.ticks = 3);
+        store.advance(Duration::from_secs(1));
+        store.recv(Action::Tick, |state| state.ticks = 4);
     }
 }
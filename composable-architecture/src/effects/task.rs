@@ -1,27 +1,219 @@
-use std::thread::Thread;
+use std::future::{poll_fn, Future, IntoFuture};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{ready, Context, Poll};
+use std::time::Instant;
 
-use futures::executor::LocalSpawner;
-use futures::future::RemoteHandle;
-use futures::task::LocalSpawnExt;
-use futures::{pin_mut, Stream, StreamExt};
+use futures::future::{AbortHandle, AbortRegistration, Abortable, RemoteHandle};
+use futures::task::{AtomicWaker, LocalSpawn, LocalSpawnExt};
+use futures::{pin_mut, FutureExt, Stream};
 
 use crate::dependencies::Dependency;
+use crate::effects::cancellation::Cancellations;
 use crate::store::channel::WeakSender;
+use crate::store::Message;
 
 /// Asynchronous work being performed by a `Store`.
 ///
-/// A [`Store`][`crate::Store`] uses a [Local Async Executor] to run its `Task`s.
+/// A [`Store`][`crate::Store`] uses a [Local Async Executor] to run its `Task`s; see
+/// [`Executor`][`crate::effects::Executor`].
 ///
 /// [Local Async Executor]: https://maciej.codes/2022-06-09-local-async.html
 ///
 /// # Cancellation
 /// Dropping a `Task` cancels the underlying future (it will not be polled again).
+///
+/// Alternatively, a `Task` can be handed to the `Store` with [`cancellable`][`Task::cancellable`]
+/// and cancelled later by its identifier, so that it does not need to be kept in the reducer’s state.
+///
+/// # Completion
+/// A `Task` can also be awaited, with [`join`][`Task::join`], until its stream ends; or be followed
+/// by an action with [`Effects::on_complete`][`crate::effects::Effects::on_complete`].
 #[doc(hidden)]
 #[derive(Debug)]
 #[must_use = "dropping a Task cancels the underlying future"]
 pub struct Task {
     pub(crate) handle: Option<RemoteHandle<()>>,
-    pub(crate) when: Option<std::time::Instant>,
+    pub(crate) when: Option<Instant>,
+    /// The next tick of a repeating task, as updated by the task itself.
+    pub(crate) next: Option<Arc<Mutex<Option<Instant>>>>,
+    /// Shared with the underlying future, which updates it as it finishes (or is cancelled).
+    pub(crate) status: Arc<Status>,
+    /// Cancels the underlying future even once the task has been detached.
+    pub(crate) abort: AbortHandle,
+}
+
+/// The most actions a task sends each time it is polled, before yielding to the rest of its `Store`.
+pub(crate) const BUDGET: usize = 32;
+
+/// Whether a task is still running, and if not, how it stopped.
+#[derive(Debug)]
+pub(crate) struct Status {
+    running: AtomicBool,
+    finished: AtomicBool,
+    /// Set for a task of [`Priority::Background`].
+    background: AtomicBool,
+    /// Woken once the task has stopped running.
+    waker: AtomicWaker,
+}
+
+/// Held by a task’s future for as long as it runs.
+pub(crate) struct Running {
+    status: Arc<Status>,
+    registration: Option<AbortRegistration>,
+}
+
+impl Running {
+    /// Returns the `Running` for a new task, along with its `Task` (which has no handle yet).
+    pub(crate) fn new() -> (Self, Task) {
+        let (abort, registration) = AbortHandle::new_pair();
+        let status = Arc::new(Status {
+            running: AtomicBool::new(true),
+            finished: AtomicBool::new(false),
+            background: AtomicBool::new(false),
+            waker: AtomicWaker::new(),
+        });
+
+        let task = Task {
+            handle: None,
+            when: None,
+            next: None,
+            status: status.clone(),
+            abort,
+        };
+
+        let running = Running {
+            status,
+            registration: Some(registration),
+        };
+
+        (running, task)
+    }
+
+    /// Returns the task’s future: which passes each item of `stream` to `send` until the stream
+    /// ends, or the task is cancelled.
+    ///
+    /// The future yields after sending [`BUDGET`] items in a row, or one for a background task.
+    /// A background task also yields, without sending anything, for as long as `busy` returns
+    /// `true`.
+    pub(crate) fn run<S: Stream>(
+        mut self,
+        stream: S,
+        mut send: impl FnMut(S::Item),
+        busy: impl Fn() -> bool,
+    ) -> impl Future<Output = ()> {
+        let registration = self.registration.take().expect("a task only runs once");
+        let future = async move {
+            pin_mut!(stream);
+
+            poll_fn(|cx| {
+                let background = self.status.background.load(Ordering::Relaxed);
+
+                if !(background && busy()) {
+                    let budget = if background { 1 } else { BUDGET };
+
+                    for _ in 0..budget {
+                        match ready!(stream.as_mut().poll_next(cx)) {
+                            Some(item) => send(item),
+                            None => return Poll::Ready(()),
+                        }
+                    }
+                }
+
+                // let the `Store`, and its other tasks, run before carrying on
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            })
+            .await;
+
+            // marks the task as having finished naturally, rather than being cancelled
+            self.status.finished.store(true, Ordering::Release);
+        };
+
+        Abortable::new(future, registration).map(|_| ())
+    }
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        self.status.running.store(false, Ordering::Release);
+        self.status.waker.wake();
+    }
+}
+
+/// How a [`Task`] stopped running.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Completion {
+    /// The task’s stream ended.
+    Finished,
+    /// The task was cancelled, or its `Store` shut down, before its stream ended.
+    Cancelled,
+}
+
+/// How a [`Task`] shares its `Store`’s thread with the `Store`’s other work.
+///
+/// A `Store` reduces every action on one thread, which also runs the tasks of its effects. A task
+/// whose stream is always ready, rather than waiting in between actions, still takes turns with
+/// the rest of the `Store`:
+///
+/// - A [`Normal`][`Priority::Normal`] task sends at most 32 actions in a row; then the `Store`
+///   reduces every action that has been sent to it, and its other tasks run, before the task
+///   carries on. An action sent with [`Store::send`] is never queued behind more than that many
+///   actions from each task.
+/// - A [`Background`][`Priority::Background`] task sends one action at a time, and none at all
+///   while any actions sent with [`Store::send`] are waiting; so that user input, for example, is
+///   never held up by it.
+///
+/// A [`TestStore`] runs every task to completion, or until it waits, on each
+/// [`advance`][`crate::TestClock::advance`]; whatever its priority.
+///
+/// See [`Task::priority`].
+///
+/// [`Store::send`]: crate::Store::send
+/// [`TestStore`]: crate::TestStore
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Priority {
+    /// The priority of every task, unless set otherwise.
+    #[default]
+    Normal,
+    /// For work, such as a long running stream, that may fall behind the `Store`’s other actions.
+    Background,
+}
+
+/// The [`Future`] returned by [`Task::join`].
+#[must_use = "dropping a Join cancels the underlying task"]
+pub struct Join(Task);
+
+impl Future for Join {
+    type Output = Completion;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Completion> {
+        let status = &self.0.status;
+        if status.running.load(Ordering::Acquire) {
+            status.waker.register(cx.waker());
+
+            // the task may have stopped before the waker was registered
+            if status.running.load(Ordering::Acquire) {
+                return Poll::Pending;
+            }
+        }
+
+        Poll::Ready(match self.0.is_finished() {
+            true => Completion::Finished,
+            false => Completion::Cancelled,
+        })
+    }
+}
+
+impl IntoFuture for Task {
+    type Output = Completion;
+    type IntoFuture = Join;
+
+    fn into_future(self) -> Join {
+        self.join()
+    }
 }
 
 impl Task {
@@ -37,40 +229,329 @@ impl Task {
         drop(self)
     }
 
+    /// Hands the task to the `Store`, to be cancelled by [`Effects::cancel`] with the same `id`.
+    ///
+    /// Any number of tasks may share an `id`. The `Store` forgets about each of them once it
+    /// finishes.
+    ///
+    /// [`Effects::cancel`]: crate::effects::Effects::cancel
+    pub fn cancellable<Id: PartialEq + 'static>(self, id: Id) {
+        if let Some(cancellations) = Dependency::<Cancellations>::get().as_deref() {
+            cancellations.insert(id, self);
+        }
+    }
+
+    /// Sets the task’s [`Priority`].
+    ///
+    /// ```rust
+    /// # use composable::*;
+    /// # use futures::StreamExt;
+    /// # #[derive(Default)]
+    /// # struct State { rows: usize }
+    /// #[derive(Clone, Debug)]
+    /// enum Action {
+    ///     Index,
+    ///     Indexed(usize),
+    /// }
+    ///
+    /// impl Reducer for State {
+    ///     type Action = Action;
+    ///     type Output = Self;
+    ///
+    ///     fn reduce(&mut self, action: Action, send: impl Effects<Action>) {
+    ///         match action {
+    ///             Action::Index => {
+    ///                 let rows = futures::stream::iter(0..100_000).map(Action::Indexed);
+    ///                 send.task(rows).priority(Priority::Background).detach()
+    ///             }
+    ///             Action::Indexed(row) => self.rows = row + 1,
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn priority(self, priority: Priority) -> Self {
+        let background = priority == Priority::Background;
+        self.status.background.store(background, Ordering::Relaxed);
+
+        self
+    }
+
+    /// Returns when the task is next due to send an action, if it was scheduled by a
+    /// [`Scheduler`]; or `None` once it has finished.
+    ///
+    /// For a repeating task, such as one from [`every`][`Scheduler::every`], this moves on as
+    /// each tick is sent.
+    ///
+    /// [`Scheduler`]: crate::effects::Scheduler
+    /// [`Scheduler::every`]: crate::effects::Scheduler::every
+    pub fn next_fire(&self) -> Option<Instant> {
+        if !self.is_running() {
+            return None;
+        }
+
+        match &self.next {
+            Some(next) => *next.lock().unwrap(),
+            None => self.when,
+        }
+    }
+
+    /// Returns a [`Future`] that resolves once the task has stopped running; reporting whether it
+    /// finished or was cancelled.
+    ///
+    /// The task keeps running while it is being joined; dropping the returned future cancels it,
+    /// as dropping the `Task` would have.
+    pub fn join(self) -> Join {
+        Join(self)
+    }
+
+    /// Returns `true` once the task’s stream has ended.
+    pub fn is_finished(&self) -> bool {
+        self.status.finished.load(Ordering::Acquire)
+    }
+
+    /// Returns `true` once the task has stopped running without its stream ending; because it was
+    /// cancelled, or its `Store` shut down.
+    pub fn is_cancelled(&self) -> bool {
+        !self.is_running() && !self.is_finished()
+    }
+
+    /// Returns `false` once the underlying future has finished.
+    pub(crate) fn is_running(&self) -> bool {
+        self.status.running.load(Ordering::Acquire)
+    }
+
+    /// Cancels the task, whether or not it has been detached.
+    ///
+    /// The underlying future is dropped the next time the `Store`’s executor runs.
+    pub(crate) fn abort(&self) {
+        self.abort.abort()
+    }
+
+    /// Returns a `Task` for the same underlying future which, being without a handle, does not
+    /// cancel it when dropped.
+    pub(crate) fn observer(&self) -> Task {
+        Task {
+            handle: None,
+            when: self.when,
+            next: self.next.clone(),
+            status: self.status.clone(),
+            abort: self.abort.clone(),
+        }
+    }
+
     pub(crate) fn new<Action: 'static, S: Stream<Item = Action> + 'static>(stream: S) -> Self {
+        let (running, mut task) = Running::new();
+
         // Only called by “root” `Effects`, so it will be the same `Action` as used by the `Store`
-        let handle =
-            Dependency::<Executor<Result<Action, Thread>>>::get().and_then(
-                |executor| match executor.actions.upgrade() {
-                    None => None,
-                    Some(sender) => executor
+        // `handle` may be `None` if the store is shutting down and the sender has been dropped.
+        task.handle = Dependency::<Spawner<Action>>::get().and_then(|spawner| {
+            match spawner.actions.upgrade() {
+                None => None,
+                Some(sender) => {
+                    // every action sent from outside of the `Store` is counted as pending
+                    let queue = sender.clone();
+                    let busy = move || queue.pending() > 0;
+
+                    let send = move |action| sender.send(Message::Effect(action));
+                    spawner
                         .spawner
-                        .spawn_local_with_handle(async move {
-                            pin_mut!(stream);
-                            while let Some(action) = stream.next().await {
-                                sender.send(Ok(action));
-                            }
-                        })
-                        .ok(),
-                },
-            );
+                        .spawn_local_with_handle(running.run(stream, send, busy))
+                        .ok()
+                }
+            }
+        });
 
-        Task {
-            // `handle` may be `None` if the store is shutting down and the sender has been dropped.
-            handle,
-            when: None,
-        }
+        task
     }
 }
 
-/// Dependency injected into a store runtime to enable spawning effect tasks.
-pub(crate) struct Executor<Action> {
-    pub(crate) spawner: LocalSpawner,
-    pub(crate) actions: WeakSender<Action>,
+/// Dependency injected into a store runtime to enable spawning effect tasks onto its
+/// [`Executor`][`crate::effects::Executor`].
+pub(crate) struct Spawner<Action> {
+    pub(crate) spawner: Rc<dyn LocalSpawn>,
+    pub(crate) actions: WeakSender<Message<Action>>,
 }
 
-impl<Action> Executor<Action> {
-    pub(crate) fn new(spawner: LocalSpawner, actions: WeakSender<Action>) -> Self {
+impl<Action> Spawner<Action> {
+    pub(crate) fn new(spawner: Rc<dyn LocalSpawn>, actions: WeakSender<Message<Action>>) -> Self {
         Self { spawner, actions }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    use std::cell::Cell;
+    use std::sync::Arc;
+
+    use futures::executor::block_on;
+    use futures::stream::{iter, pending};
+    use futures::task::{waker, ArcWake};
+
+    use super::*;
+    use crate::{Effects, Reducer, TestClock, TestStore};
+
+    #[derive(Clone, Debug, Default)]
+    struct State {
+        log: Vec<u32>,
+        task: Rc<RefCell<Option<Task>>>,
+    }
+
+    impl PartialEq for State {
+        fn eq(&self, other: &Self) -> bool {
+            self.log == other.log
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Action {
+        Upload,
+        Forever,
+        Stop,
+        Progress(u32),
+        Uploaded,
+    }
+
+    impl Reducer for State {
+        type Action = Action;
+        type Output = Self;
+
+        fn reduce(&mut self, action: Action, send: impl Effects<Action>) {
+            match action {
+                Action::Upload => {
+                    let upload = send.task(iter([Action::Progress(50), Action::Progress(100)]));
+                    *self.task.borrow_mut() = Some(send.on_complete(upload, Action::Uploaded));
+                }
+                Action::Forever => send
+                    .on_complete(send.task(pending()), Action::Uploaded)
+                    .cancellable("forever"),
+                Action::Stop => send.cancel("forever"),
+                Action::Progress(n) => self.log.push(n),
+                Action::Uploaded => self.log.push(0),
+            }
+        }
+    }
+
+    #[test]
+    fn test_on_complete() {
+        let state = State::default();
+        let task = state.task.clone();
+        let mut store = TestStore::with_initial(state);
+
+        store.send(Action::Upload, |_| {});
+        store.advance(Duration::ZERO);
+        store.recv(Action::Progress(50), |state| state.log = vec![50]);
+        store.recv(Action::Progress(100), |state| state.log = vec![50, 100]);
+
+        store.advance(Duration::ZERO);
+        store.recv(Action::Uploaded, |state| state.log = vec![50, 100, 0]);
+
+        let task = task.borrow_mut().take().unwrap();
+        assert!(task.is_finished());
+        assert!(!task.is_cancelled());
+        assert_eq!(block_on(task.join()), Completion::Finished);
+    }
+
+    #[test]
+    fn test_on_complete_is_not_sent_when_cancelled() {
+        let mut store = TestStore::<State>::default();
+
+        store.send(Action::Forever, |_| {});
+        store.advance(Duration::ZERO);
+
+        store.send(Action::Stop, |_| {});
+        store.advance(Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_join_reports_cancellation() {
+        let state = State::default();
+        let task = state.task.clone();
+        let mut store = TestStore::with_initial(state);
+
+        store.send(Action::Upload, |_| {});
+        let task = task.borrow_mut().take().unwrap();
+        assert!(!task.is_finished() && !task.is_cancelled());
+
+        drop(store); // along with its executor, before the task could run
+        assert!(task.is_cancelled());
+        assert_eq!(block_on(task.into_future()), Completion::Cancelled);
+    }
+
+    #[derive(Default)]
+    struct Woken(AtomicBool);
+
+    impl ArcWake for Woken {
+        fn wake_by_ref(arc_self: &Arc<Self>) {
+            arc_self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    impl Woken {
+        fn take(&self) -> bool {
+            self.0.swap(false, Ordering::SeqCst)
+        }
+    }
+
+    #[test]
+    fn test_tasks_yield_once_their_budget_is_spent() {
+        let (running, _task) = Running::new();
+        let sent = Rc::new(Cell::new(0));
+
+        let counter = sent.clone();
+        let future = running.run(
+            iter(0..BUDGET + 1),
+            move |_| counter.set(counter.get() + 1),
+            || false,
+        );
+        pin_mut!(future);
+
+        let woken = Arc::new(Woken::default());
+        let waker = waker(woken.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        assert!(future.as_mut().poll(&mut cx).is_pending());
+        assert_eq!(sent.get(), BUDGET);
+        assert!(woken.take()); // to carry on as soon as the `Store` has had a turn
+
+        assert!(future.as_mut().poll(&mut cx).is_ready());
+        assert_eq!(sent.get(), BUDGET + 1);
+    }
+
+    #[test]
+    fn test_background_tasks_wait_while_the_store_is_busy() {
+        let (running, task) = Running::new();
+        let _task = task.priority(Priority::Background);
+        let sent = Rc::new(Cell::new(0));
+        let busy = Rc::new(Cell::new(true));
+
+        let counter = sent.clone();
+        let queued = busy.clone();
+        let future = running.run(
+            iter(0..2),
+            move |_| counter.set(counter.get() + 1),
+            move || queued.get(),
+        );
+        pin_mut!(future);
+
+        let woken = Arc::new(Woken::default());
+        let waker = waker(woken.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        assert!(future.as_mut().poll(&mut cx).is_pending());
+        assert_eq!(sent.get(), 0);
+        assert!(woken.take());
+
+        busy.set(false);
+        assert!(future.as_mut().poll(&mut cx).is_pending());
+        assert_eq!(sent.get(), 1); // one at a time
+        assert!(woken.take());
+
+        assert!(future.as_mut().poll(&mut cx).is_pending());
+        assert_eq!(sent.get(), 2);
+        assert!(future.as_mut().poll(&mut cx).is_ready());
+    }
+}
@@ -0,0 +1,161 @@
+//! Task scopes: groups of tasks that are cancelled together.
+//!
+//! A child feature that can go away (a row of a list, a presented sheet) usually starts tasks that
+//! should go away with it. Rather than the parent keeping track of each of them, the child’s tasks
+//! are added to a [`TaskScope`] that is cancelled as the child’s state is removed.
+//!
+//! Tasks started through [`Effects::scope_keyed`](crate::effects::Effects::scope_keyed) are added to
+//! the scope of their key automatically, and [`KeyedState::remove`](crate::KeyedState::remove)
+//! cancels it.
+
+use std::marker::PhantomData;
+
+use crate::dependencies::Dependency;
+use crate::effects::cancellation::Cancellations;
+use crate::effects::Task;
+use crate::Keyed;
+
+/// A group of tasks, identified by an `Id`, that are cancelled together.
+///
+/// Like [`Task::cancellable`], a `TaskScope` is kept by the `Store`, so reducer state only needs the
+/// identifier. Unlike it, the tasks are handed back, and remain in the scope even if they are
+/// [`detach`][`Task::detach`]ed.
+///
+/// ```rust
+/// # use composable::*;
+/// # use composable::effects::TaskScope;
+/// #[derive(Default)]
+/// struct State {
+///     sheet: Option<String>,
+/// }
+///
+/// #[derive(Clone, Debug)]
+/// enum Action {
+///     Present(String),
+///     Dismiss,
+///     Tick,
+/// }
+///
+/// impl Reducer for State {
+///     type Action = Action;
+///     type Output = Self;
+///
+///     fn reduce(&mut self, action: Action, send: impl Effects<Action>) {
+///         let scope = TaskScope::new("sheet");
+///
+///         match action {
+///             Action::Present(name) => {
+///                 self.sheet = Some(name);
+///
+///                 let interval = Interval::Trailing(std::time::Duration::from_secs(1));
+///                 scope.add(send.every(interval, Action::Tick)).detach();
+///             }
+///             Action::Dismiss => {
+///                 self.sheet = None;
+///                 scope.cancel();
+///             }
+///             Action::Tick => {}
+///         }
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct TaskScope<Id>(Id);
+
+impl<Id: Clone + PartialEq + 'static> TaskScope<Id> {
+    /// The scope identified by `id`; any number of `TaskScope`s with the same `id` are the same
+    /// scope.
+    pub fn new(id: Id) -> Self {
+        TaskScope(id)
+    }
+
+    /// Adds `task` to the scope, and returns it.
+    ///
+    /// Tasks that finish are removed from the scope by themselves.
+    pub fn add(&self, task: Task) -> Task {
+        if let Some(cancellations) = Dependency::<Cancellations>::get().as_deref() {
+            cancellations.insert(self.clone(), task.observer());
+        }
+
+        task
+    }
+
+    /// Cancels every task in the scope.
+    pub fn cancel(&self) {
+        if let Some(cancellations) = Dependency::<Cancellations>::get().as_deref() {
+            cancellations.cancel(self);
+        }
+    }
+}
+
+/// The identifier of a keyed child’s scope; which includes the type of its actions, so that the
+/// children of different keyed collections do not share scopes.
+type KeyedId<K, Child> = Keyed<K, PhantomData<fn() -> Child>>;
+
+/// The scope of the tasks started through `scope_keyed(key)` by a child with `Child` actions.
+pub(crate) fn keyed<K, Child>(key: K) -> TaskScope<KeyedId<K, Child>> {
+    TaskScope(Keyed::new(key, PhantomData))
+}
+
+/// Cancels the tasks of a keyed child that no longer exists.
+///
+/// Used by `#[derive(RecursiveReducer)]` when an action arrives for a missing key; in case the
+/// child was removed without [`KeyedState::remove`](crate::KeyedState::remove).
+#[doc(hidden)]
+pub fn cancel_keyed<K: Clone + PartialEq + 'static, Child: 'static>(key: K, _action: &Child) {
+    keyed::<K, Child>(key).cancel()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{Effects, Interval, Reducer, TestClock, TestStore};
+
+    #[derive(Clone, Debug, Default, PartialEq)]
+    struct State {
+        ticks: usize,
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Action {
+        Present,
+        Dismiss,
+        Tick,
+    }
+
+    impl Reducer for State {
+        type Action = Action;
+        type Output = Self;
+
+        fn reduce(&mut self, action: Action, send: impl Effects<Action>) {
+            let scope = TaskScope::new("sheet");
+
+            match action {
+                Action::Present => {
+                    let interval = Interval::Trailing(Duration::from_secs(1));
+                    scope.add(send.every(interval, Action::Tick)).detach();
+                    scope
+                        .add(send.after(Duration::from_secs(10), Action::Tick))
+                        .detach();
+                }
+                Action::Dismiss => scope.cancel(),
+                Action::Tick => self.ticks += 1,
+            }
+        }
+    }
+
+    #[test]
+    fn test_cancel_detached_tasks() {
+        let mut store = TestStore::<State>::default();
+
+        store.send(Action::Present, |_| {});
+        store.advance(Duration::ZERO);
+        store.advance(Duration::from_secs(1));
+        store.recv(Action::Tick, |state| state.ticks = 1);
+
+        store.send(Action::Dismiss, |_| {});
+        store.advance(Duration::from_secs(10)); // neither task sends anything
+    }
+}
@@ -0,0 +1,238 @@
+//! Tick policies for [`Scheduler::every_with`](crate::effects::Scheduler::every_with).
+//!
+//! The ticks of an interval are computed from when it started, rather than from when the previous
+//! tick was sent, so that an interval does not drift. A tick that is sent late, because the
+//! `Store` was busy for example, can leave the ticks after it overdue as well; a [`MissedTicks`]
+//! policy decides what happens to them.
+
+use std::time::{Duration, Instant};
+
+/// What a repeating interval does when it finds that its next tick is already overdue.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MissedTicks {
+    /// Sends every overdue tick, back to back, to catch up with the original schedule.
+    #[default]
+    Burst,
+    /// Drops the overdue ticks and waits for the next tick of the original schedule.
+    Skip,
+    /// Drops the overdue ticks and restarts the interval from the late tick; so every later tick
+    /// is delayed by as much as it was.
+    Delay,
+}
+
+/// How a repeating interval handles missed ticks, along with any jitter added to each tick.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TickPolicy {
+    pub(crate) missed: MissedTicks,
+    pub(crate) jitter: Duration,
+}
+
+impl TickPolicy {
+    /// Handles overdue ticks with `missed`, without any jitter.
+    pub fn new(missed: MissedTicks) -> Self {
+        Self {
+            missed,
+            jitter: Duration::ZERO,
+        }
+    }
+
+    /// Sends each tick up to `max` later than scheduled, by a random amount; so that many
+    /// intervals started at once do not all tick at the same moment.
+    ///
+    /// The jitter of one tick does not move the ticks after it. It is drawn from the `Store`’s
+    /// reactor; a [`TestStore`](crate::TestStore) draws the same jitter on every run.
+    pub fn jitter(mut self, max: Duration) -> Self {
+        self.jitter = max;
+        self
+    }
+}
+
+/// The deadlines of a repeating interval.
+pub(crate) struct Ticks {
+    policy: TickPolicy,
+    start: Instant,
+    period: Duration,
+    /// The number of the next tick, counting from `start`.
+    n: u32,
+    started: bool,
+}
+
+impl Ticks {
+    pub(crate) fn new(policy: TickPolicy, start: Instant, period: Duration, n: u32) -> Self {
+        Self {
+            policy,
+            start,
+            period,
+            n,
+            started: false,
+        }
+    }
+
+    /// Returns when the next tick is due, as of `now`; jittered by `random`, in `0.0..1.0`.
+    ///
+    /// `paused` is the time the interval has spent with its timers paused, which pushes back
+    /// every tick after it.
+    pub(crate) fn next(
+        &mut self,
+        now: Instant,
+        paused: Duration,
+        random: impl FnOnce() -> f64,
+    ) -> Option<Instant> {
+        let start = self.start.checked_add(paused)?;
+        let mut deadline = start.checked_add(self.period.checked_mul(self.n)?)?;
+
+        // the first tick is never overdue; it may simply be leading
+        if self.started && deadline <= now {
+            match self.policy.missed {
+                MissedTicks::Burst => {}
+                MissedTicks::Skip => {
+                    let elapsed = now.duration_since(start).as_nanos();
+                    let period = self.period.as_nanos().max(1);
+
+                    self.n = u32::try_from(elapsed / period + 1).ok()?;
+                    deadline = start.checked_add(self.period.checked_mul(self.n)?)?;
+                }
+                MissedTicks::Delay => {
+                    self.start = now.checked_sub(paused)?;
+                    self.n = 1;
+                    deadline = now.checked_add(self.period)?;
+                }
+            }
+        }
+
+        self.started = true;
+        self.n = self.n.checked_add(1)?;
+
+        match self.policy.jitter {
+            jitter if jitter.is_zero() => Some(deadline),
+            jitter => deadline.checked_add(jitter.mul_f64(random())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::dependencies::Dependency;
+    use crate::effects::scheduler::Reactor;
+    use crate::{Effects, Interval, Reducer, Task, TestClock, TestStore};
+
+    #[derive(Clone, Debug, Default)]
+    struct State {
+        ticks: usize,
+        task: Rc<RefCell<Option<Task>>>,
+    }
+
+    impl PartialEq for State {
+        fn eq(&self, other: &Self) -> bool {
+            self.ticks == other.ticks
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Action {
+        Start(TickPolicy),
+        Idle, // fails to `send` while a tick is still waiting to be received
+        Tick,
+    }
+
+    impl Reducer for State {
+        type Action = Action;
+        type Output = Self;
+
+        fn reduce(&mut self, action: Action, send: impl Effects<Action>) {
+            match action {
+                Action::Start(policy) => {
+                    let interval = Interval::Trailing(Duration::from_secs(1));
+                    let task = send.every_with(interval, policy, Action::Tick);
+                    *self.task.borrow_mut() = Some(task);
+                }
+                Action::Idle => {}
+                Action::Tick => self.ticks += 1,
+            }
+        }
+    }
+
+    /// Starts an interval of one second, then stalls for 3½ seconds before its first tick.
+    fn stalled(policy: TickPolicy) -> (TestStore<State>, Rc<RefCell<Option<Task>>>, Instant) {
+        let state = State::default();
+        let task = state.task.clone();
+        let mut store = TestStore::with_initial(state);
+        let start = Dependency::<Reactor>::get().now();
+
+        store.send(Action::Start(policy), |_| {});
+        store.advance(Duration::from_millis(3500));
+        store.recv(Action::Tick, |state| state.ticks = 1);
+
+        (store, task, start)
+    }
+
+    fn next_fire(task: &Rc<RefCell<Option<Task>>>) -> Option<Instant> {
+        task.borrow().as_ref().and_then(Task::next_fire)
+    }
+
+    #[test]
+    fn test_burst_catches_up() {
+        let (mut store, task, start) = stalled(TickPolicy::new(MissedTicks::Burst));
+
+        // a `TestStore` sends at most one tick of each task per `advance`
+        store.advance(Duration::ZERO);
+        store.recv(Action::Tick, |state| state.ticks = 2);
+        store.advance(Duration::ZERO);
+        store.recv(Action::Tick, |state| state.ticks = 3);
+        assert_eq!(next_fire(&task), Some(start + Duration::from_secs(4)));
+
+        store.advance(Duration::from_millis(500));
+        store.recv(Action::Tick, |state| state.ticks = 4);
+    }
+
+    #[test]
+    fn test_skip_to_the_next_aligned_tick() {
+        let (mut store, task, start) = stalled(TickPolicy::new(MissedTicks::Skip));
+        assert_eq!(next_fire(&task), Some(start + Duration::from_secs(4)));
+
+        store.advance(Duration::from_millis(499));
+        store.send(Action::Idle, |_| {});
+
+        store.advance(Duration::from_millis(1));
+        store.recv(Action::Tick, |state| state.ticks = 2);
+    }
+
+    #[test]
+    fn test_delay_restarts_the_interval() {
+        let (mut store, task, start) = stalled(TickPolicy::new(MissedTicks::Delay));
+        assert_eq!(next_fire(&task), Some(start + Duration::from_millis(4500)));
+
+        store.advance(Duration::from_millis(999));
+        store.send(Action::Idle, |_| {});
+
+        store.advance(Duration::from_millis(1));
+        store.recv(Action::Tick, |state| state.ticks = 2);
+        assert_eq!(next_fire(&task), Some(start + Duration::from_millis(5500)));
+    }
+
+    #[test]
+    fn test_jitter_does_not_drift() {
+        let jitter = Duration::from_millis(500);
+        let (mut store, task, start) = stalled(TickPolicy::new(MissedTicks::Skip).jitter(jitter));
+
+        // the jitter that the `TestStore`’s reactor will draw, as it has the same seed
+        let reactor = Reactor::new();
+        reactor.random(); // drawn for the first tick
+
+        for tick in 4..10 {
+            let next = next_fire(&task).unwrap();
+            let aligned = start + Duration::from_secs(tick);
+            assert_eq!(next, aligned + jitter.mul_f64(reactor.random()));
+
+            store.advance(next - Dependency::<Reactor>::get().now());
+            store.recv(Action::Tick, |state| state.ticks += 1);
+        }
+
+        task.borrow_mut().take().unwrap().cancel();
+        assert_eq!(next_fire(&task), None);
+    }
+}
@@ -0,0 +1,295 @@
+//! A bounded pool of worker threads for the blocking, or `Send`, work of effects.
+//!
+//! A `Store` runs its effects on its own thread, so an effect that blocks stalls every other
+//! action. [`Effects::blocking`] and [`Effects::spawn_send`] hand such work to the [`Workers`]
+//! dependency instead. The task left on the `Store`’s thread only waits for the result, and sends
+//! it on like any other effect’s action.
+//!
+//! A [`TestStore`](crate::TestStore) registers workers that run the work inline, on its own
+//! executor, so that tests remain deterministic.
+//!
+//! [`Effects::blocking`]: crate::effects::Effects::blocking
+//! [`Effects::spawn_send`]: crate::effects::Effects::spawn_send
+
+use std::future::Future;
+use std::num::NonZeroUsize;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{available_parallelism, Builder};
+
+use futures::channel::oneshot;
+use futures::executor::block_on;
+use futures::future::{select, Either, LocalBoxFuture};
+use futures::{pin_mut, FutureExt};
+
+use crate::dependencies::DependencyDefault;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// The worker threads that run [`blocking`] and [`spawn_send`] effects.
+///
+/// By default, each `Store` thread that needs them starts one worker per available CPU. A different
+/// number can be registered as a dependency:
+///
+/// ```rust
+/// # use composable::*;
+/// # #[derive(Default)]
+/// # struct State;
+/// # impl Reducer for State {
+/// #     type Action = ();
+/// #     type Output = Self;
+/// #     fn reduce(&mut self, action: (), send: impl Effects<()>) {}
+/// # }
+/// use composable::effects::Workers;
+///
+/// let store = Store::with_dependency(State::default, || Workers::new(2));
+/// ```
+///
+/// The workers stop once they have been dropped and their queued work is done.
+///
+/// [`blocking`]: crate::effects::Effects::blocking
+/// [`spawn_send`]: crate::effects::Effects::spawn_send
+pub struct Workers {
+    /// `None` for workers that run their work inline.
+    jobs: Option<Sender<Job>>,
+}
+
+impl Default for Workers {
+    fn default() -> Self {
+        Self::new(available_parallelism().map_or(1, NonZeroUsize::get))
+    }
+}
+
+impl DependencyDefault for Workers {}
+
+impl Workers {
+    /// Starts `threads` worker threads.
+    ///
+    /// # Panics
+    /// If `threads` is zero.
+    pub fn new(threads: usize) -> Self {
+        assert!(threads > 0, "there must be at least one worker");
+
+        let (jobs, queue) = channel::<Job>();
+        let queue = Arc::new(Mutex::new(queue));
+
+        for _ in 0..threads {
+            let queue = queue.clone();
+
+            Builder::new()
+                .name(std::any::type_name::<Self>().into())
+                .spawn(move || {
+                    while let Some(job) = next(&queue) {
+                        // a panic loses the job’s action, but not the worker
+                        catch_unwind(AssertUnwindSafe(job)).ok();
+                    }
+                })
+                .expect("worker thread");
+        }
+
+        Self { jobs: Some(jobs) }
+    }
+
+    /// Workers that run their work on the current thread, as its result is awaited.
+    pub(crate) fn inline() -> Self {
+        Self { jobs: None }
+    }
+
+    /// Runs `work` on a worker thread; the returned future resolves to its result.
+    ///
+    /// The result is `None` if `work` panicked. Work that has not yet started when the future is
+    /// dropped is skipped.
+    pub(crate) fn run<T, F>(&self, work: F) -> LocalBoxFuture<'static, Option<T>>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let Some(jobs) = &self.jobs else {
+            return async move { Some(work()) }.boxed_local();
+        };
+
+        let (result, received) = oneshot::channel();
+        let job = move || {
+            if !result.is_canceled() {
+                result.send(work()).ok();
+            }
+        };
+
+        jobs.send(Box::new(job)).ok();
+        received.map(Result::ok).boxed_local()
+    }
+
+    /// Runs `future` to completion on a worker thread; the returned future resolves to its output.
+    ///
+    /// The result is `None` if `future` panicked. Dropping the returned future stops `future` the
+    /// next time it yields.
+    pub(crate) fn spawn<F>(&self, future: F) -> LocalBoxFuture<'static, Option<F::Output>>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let Some(jobs) = &self.jobs else {
+            return future.map(Some).boxed_local();
+        };
+
+        let (mut result, received) = oneshot::channel();
+        let job = move || {
+            pin_mut!(future);
+
+            let output = match block_on(select(future, result.cancellation())) {
+                Either::Left((output, _)) => Some(output),
+                Either::Right(_) => None, // the effect was cancelled
+            };
+
+            if let Some(output) = output {
+                result.send(output).ok();
+            }
+        };
+
+        jobs.send(Box::new(job)).ok();
+        received.map(Result::ok).boxed_local()
+    }
+}
+
+/// Returns the next job, or `None` once the `Workers` have been dropped.
+fn next(queue: &Mutex<Receiver<Job>>) -> Option<Job> {
+    // only a panic in `recv` itself could poison the lock
+    let queue = queue.lock().unwrap_or_else(|err| err.into_inner());
+    queue.recv().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+    use std::thread::{current, ThreadId};
+    use std::time::Duration;
+
+    use futures::StreamExt;
+    #[cfg(not(miri))]
+    use ntest_timeout::timeout;
+
+    use super::*;
+    use crate::{Effects, Reducer, Store, TestClock, TestStore};
+
+    #[derive(Clone, Debug, Default, PartialEq)]
+    struct State {
+        threads: Vec<ThreadId>,
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Action {
+        Blocking,
+        Spawned,
+        RanOn(ThreadId),
+    }
+
+    impl Reducer for State {
+        type Action = Action;
+        type Output = Self;
+
+        fn reduce(&mut self, action: Action, send: impl Effects<Action>) {
+            match action {
+                Action::Blocking => send.blocking(|| Action::RanOn(current().id())).detach(),
+                Action::Spawned => send
+                    .spawn_send(async { Some(Action::RanOn(current().id())) })
+                    .detach(),
+                Action::RanOn(thread) => self.threads.push(thread),
+            }
+        }
+    }
+
+    #[test]
+    fn test_test_store_runs_work_inline() {
+        let mut store = TestStore::<State>::default();
+        let thread = current().id();
+
+        store.send(Action::Blocking, |_| {});
+        store.send(Action::Spawned, |_| {});
+        store.advance(Duration::ZERO);
+
+        store.recv(Action::RanOn(thread), |state| state.threads = vec![thread]);
+        store.recv(Action::RanOn(thread), |state| {
+            state.threads = vec![thread, thread]
+        });
+    }
+
+    #[test]
+    fn test_cancelled_work_is_skipped() {
+        let workers = Workers::new(1);
+        let (release, blocked) = mpsc::channel::<()>();
+        let (ran, check) = mpsc::channel();
+
+        // keeps the only worker busy until released
+        let first = workers.run(move || blocked.recv().ok());
+        let second = workers.run(move || ran.send(()).ok());
+
+        drop(second);
+        release.send(()).unwrap();
+        assert_eq!(block_on(first), Some(Some(())));
+        assert!(check.recv().is_err()); // `ran` was dropped without being sent
+    }
+
+    #[test]
+    #[cfg(not(miri))]
+    #[timeout(10000)]
+    fn test_blocking_work_does_not_stall_the_store() {
+        #[derive(Default)]
+        struct State {
+            blocked: Option<mpsc::Receiver<()>>,
+            pings: usize,
+            done: usize,
+        }
+
+        #[derive(Debug)]
+        enum Action {
+            Block,
+            Spawn,
+            Ping,
+            Done,
+        }
+
+        impl Reducer for State {
+            type Action = Action;
+            type Output = Self;
+
+            fn reduce(&mut self, action: Action, send: impl Effects<Action>) {
+                match action {
+                    Action::Block => {
+                        let blocked = self.blocked.take().unwrap();
+                        send.blocking(move || {
+                            blocked.recv().unwrap();
+                            Action::Done
+                        })
+                        .detach()
+                    }
+                    Action::Spawn => send.spawn_send(async { Some(Action::Done) }).detach(),
+                    Action::Ping => self.pings += 1,
+                    Action::Done => self.done += 1,
+                }
+            }
+        }
+
+        let (release, blocked) = mpsc::channel();
+        let store = Store::with_dependency(
+            || State {
+                blocked: Some(blocked),
+                ..Default::default()
+            },
+            || Workers::new(2),
+        );
+        let progress = store.subscribe(|state| (state.pings, state.done));
+
+        store.send(Action::Block);
+        store.sync(Action::Ping).unwrap(); // while the worker is still blocked
+        store.send(Action::Spawn);
+        release.send(()).unwrap();
+
+        let progress = block_on(
+            progress
+                .filter(|&(_, done)| std::future::ready(done == 2))
+                .next(),
+        );
+        assert_eq!(progress, Some((1, 2)));
+    }
+}
@@ -54,6 +54,9 @@ use std::hash::Hash;
 use std::marker::PhantomData;
 use std::ops::{Deref, DerefMut};
 
+use crate::effects::task_scope::keyed;
+use crate::Reducer;
+
 /// A keyed wrapper around an action `A` for a particular child identified by `K`.
 ///
 /// This is typically embedded inside a parent `Action` enum to represent the “child actions”
@@ -155,6 +158,47 @@ where
     }
 }
 
+impl<K, V> KeyedState<K, V, HashMap<K, V>>
+where
+    K: Clone + Eq + Hash + 'static,
+    V: Reducer,
+    <V as Reducer>::Action: 'static,
+{
+    /// Removes the child state for `key`, cancelling the tasks the child started through
+    /// `Effects::scope_keyed(key)`.
+    ///
+    /// Removing a child in any other way leaves its tasks running until an action arrives for it.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        cancel_tasks::<K, V>(key);
+        HashMap::remove(&mut self.0, key)
+    }
+}
+
+impl<K, V> KeyedState<K, V, BTreeMap<K, V>>
+where
+    K: Clone + Ord + 'static,
+    V: Reducer,
+    <V as Reducer>::Action: 'static,
+{
+    /// Removes the child state for `key`, cancelling the tasks the child started through
+    /// `Effects::scope_keyed(key)`.
+    ///
+    /// Removing a child in any other way leaves its tasks running until an action arrives for it.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        cancel_tasks::<K, V>(key);
+        BTreeMap::remove(&mut self.0, key)
+    }
+}
+
+fn cancel_tasks<K, V>(key: &K)
+where
+    K: Clone + PartialEq + 'static,
+    V: Reducer,
+    <V as Reducer>::Action: 'static,
+{
+    keyed::<K, <V as Reducer>::Action>(key.clone()).cancel();
+}
+
 /// A small abstraction over “map-like” keyed storage used by [`KeyedState`].
 ///
 /// This is intentionally minimal: the derive macro only needs `get_mut` in order to route a child
@@ -8,9 +8,15 @@
 #[doc(no_inline)]
 pub use derive_macros::*;
 #[doc(inline)]
-pub use effects::{Interval, Task};
+pub use effects::{
+    Backoff, Completion, Interval, MissedTicks, Priority, Resume, RetryPolicy, Task, TickPolicy,
+};
 pub use reducer::Reducer;
-pub use store::{testing::TestClock, testing::TestStore, Store};
+pub use store::{
+    recording, Backpressure, Dispatch, LocalStore, Middleware, PanicPolicy, SendError, Store,
+    StoreError, Subscription,
+};
+pub use store::{testing::Exhaustivity, testing::TestClock, testing::TestStore};
 pub mod dependencies;
 
 #[path = "../../about/mod.rs"]
@@ -1,223 +1,931 @@
-//! A small single-consumer channel with a synchronous “barrier” mode.
+//! A small single-consumer channel with a synchronous send.
 //!
 //! This is tailored for `Store`:
 //!
 //! - Many senders can enqueue values.
 //! - A single receiver stream drains them.
-//! - The receiver maintains an internal buffer to minimise time spent holding the mutex.
+//! - Values are queued without locking, on a lock-free linked list (that of [`std::sync::mpsc`]);
+//!   so senders on many threads do not contend with each other, or with the receiver, for a
+//!   `Mutex`.
 //! - `Sender::sync` provides a blocking send that only returns once the receiver has advanced
-//!   past the sent value.
+//!   past the sent value. The receiver fires a one-shot notification as it does, so that it never
+//!   waits on the sender itself; or returns the value through it, if it never receives it. The sender waits on it without entering an executor, so `sync`
+//!   may be called from within one; such as another `Store`’s runtime.
+//! - `Sender::send_and_wait` provides the same guarantee asynchronously.
+//!
+//! Values are received in the order that they were queued, across all senders.
+//!
+//! # Capacity
+//! Values sent with `Sender::send_bounded` are *counted*; the channel keeps track of how many are
+//! queued and, once a capacity has been set, applies a [`Backpressure`] policy when it is reached.
+//! All other values bypass the capacity entirely.
+//!
+//! A policy may need to drop, or replace, values that are already queued; so once a capacity has
+//! been set values are queued behind a `Mutex` instead, where every queued value remains visible
+//! to the policy. (Values queued before then are received first; the receiver waits for any sender
+//! still queuing on the lock-free list before it takes from behind the `Mutex`.)
+//!
+//! # Closing
+//! Dropping the receiver closes the channel. Values queued at that point are dropped (returning
+//! those of any `sync` callers) and subsequent values are rejected as they are sent.
 //!
 //! # Waker behaviour
 //! The receiver stores at most one `Waker`, and each transition into `Poll::Pending` consumes it
 //! exactly once. This avoids “extra” wakes when many values are sent quickly.
 
-use futures::Stream;
-use pin_project::pin_project;
+use futures::channel::oneshot;
+use futures::task::AtomicWaker;
+use futures::{Future, Stream};
 use std::collections::VecDeque;
-use std::sync::{Arc, Barrier, Mutex, MutexGuard, Weak};
-use std::task::{Context, Poll, Waker};
-use std::{mem::swap, pin::Pin};
+use std::sync::atomic::{fence, AtomicBool, AtomicUsize, Ordering::SeqCst};
+use std::sync::{mpsc, Arc, Condvar, Mutex, MutexGuard, OnceLock, Weak};
+use std::task::{Context, Poll};
+use std::thread::{current, panicking, ThreadId};
+use std::{mem::take, pin::Pin};
+
+use crate::store::SendError;
 
 enum Msg<T> {
     Value(T),
-    Barrier(Arc<Barrier>),
+    Counted(T),
+    Waited(Waited<T>),
+}
+
+/// A value whose sender is waiting for the receiver to finish with it.
+struct Waited<T> {
+    /// Taken by the receiver.
+    value: Option<T>,
+    notify: Option<Notify<T>>,
+}
+
+impl<T> Waited<T> {
+    fn new(value: T, notify: Notify<T>) -> Self {
+        Waited {
+            value: Some(value),
+            notify: Some(notify),
+        }
+    }
+}
+
+impl<T> Drop for Waited<T> {
+    fn drop(&mut self) {
+        // the receiver never got to the value, so its sender gets it back
+        if let (Some(value), Some(notify)) = (self.value.take(), self.notify.take()) {
+            notify.fire(Err(Some(value)));
+        }
+    }
+}
+
+impl<T> Msg<T> {
+    /// Takes the value back from a `Waited` message that could not be queued.
+    fn into_waited(self) -> T {
+        match self {
+            Msg::Waited(mut waited) => waited.value.take().unwrap(),
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// The one-shot notification of a sender waiting for the receiver: `Ok` once the receiver has
+/// finished with its value, `Err` with the value if it never received it, or `Err(None)` if the
+/// receiver’s thread panicked before it had finished with it.
+enum Notify<T> {
+    /// Awaited by [`Sender::send_and_wait`].
+    Future(oneshot::Sender<Notified<T>>),
+    /// Blocked on by [`Sender::sync`].
+    Thread(mpsc::SyncSender<Notified<T>>),
+}
+
+type Notified<T> = Result<(), Option<T>>;
+
+impl<T> Notify<T> {
+    fn fire(self, result: Notified<T>) {
+        // the waiting sender may have gone already
+        match self {
+            Notify::Future(notify) => notify.send(result).ok(),
+            Notify::Thread(notify) => notify.send(result).ok(),
+        };
+    }
+
+    /// Converts the notification received by a waiting sender into its result.
+    fn result<E>(notified: Result<Notified<T>, E>) -> Result<(), SendError<T>> {
+        match notified {
+            Ok(Err(Some(value))) => Err(SendError::Disconnected(value)),
+            Ok(Err(None)) => Err(SendError::Panicked),
+            _ => Ok(()), // the notification is always fired, one way or the other
+        }
+    }
+}
+
+/// What a bounded [`Store`](crate::Store) does when an action is sent while its queue is full.
+///
+/// Only actions sent from outside of the `Store` count towards its capacity; actions sent by its
+/// own effects are always accepted.
+pub enum Backpressure<Action> {
+    /// Block the sending thread until there is room in the queue.
+    Block,
+    /// Drop the action being sent.
+    DropNewest,
+    /// Drop the oldest queued action to make room for the action being sent.
+    DropOldest,
+    /// Replace a queued action for which the function returns `true` with the action being sent,
+    /// keeping its place in the queue. This happens whether or not the queue is full.
+    ///
+    /// If there is no such action and the queue is full, the oldest queued action is dropped.
+    ///
+    /// See [`coalesce_by`][`Backpressure::coalesce_by`].
+    #[allow(clippy::type_complexity)]
+    Coalesce(Box<dyn Fn(&Action, &Action) -> bool + Send + Sync>),
+}
+
+impl<Action> Backpressure<Action> {
+    /// Coalesces actions that have the same `key`.
+    pub fn coalesce_by<K, F>(key: F) -> Self
+    where
+        K: PartialEq,
+        F: Fn(&Action) -> K + Send + Sync + 'static,
+    {
+        Backpressure::Coalesce(Box::new(move |queued, new| key(queued) == key(new)))
+    }
+
+    /// Converts the policy to one for values containing an `Action`.
+    pub(crate) fn map<T, F>(self, f: F) -> Backpressure<T>
+    where
+        Action: 'static,
+        F: Fn(&T) -> Option<&Action> + Send + Sync + 'static,
+    {
+        match self {
+            Backpressure::Block => Backpressure::Block,
+            Backpressure::DropNewest => Backpressure::DropNewest,
+            Backpressure::DropOldest => Backpressure::DropOldest,
+            Backpressure::Coalesce(same) => {
+                Backpressure::Coalesce(Box::new(move |queued, new| match (f(queued), f(new)) {
+                    (Some(queued), Some(new)) => same(queued, new),
+                    _ => false,
+                }))
+            }
+        }
+    }
+}
+
+/// State shared by both ends of the channel.
+struct Channel<T> {
+    /// The lock-free queue; used until a capacity is set.
+    queue: mpsc::Sender<Msg<T>>,
+    /// The queue of a bounded channel, set once a capacity is.
+    bounded: OnceLock<Bounded<T>>,
+    waker: AtomicWaker,
+    /// Set by the receiver before it waits, and cleared by the sender that wakes it.
+    waiting: AtomicBool,
+    senders: AtomicUsize,
+    closed: AtomicBool,
+    /// The number of counted values that have been sent but not yet received.
+    depth: AtomicUsize,
+    /// The number of values whose senders wait for them, that have been sent but not yet received.
+    waited: AtomicUsize,
+    /// The number of senders waiting on `space`.
+    blocked: AtomicUsize,
+    /// The number of senders that may be queuing on the lock-free `queue`; the receiver does not
+    /// take from the `bounded` queue until they have finished.
+    pushing: AtomicUsize,
+    /// The thread that the receiver was upgraded on.
+    receiver: OnceLock<ThreadId>,
+}
+
+impl<T> Channel<T> {
+    /// Queues `msg` and, if the receiver was waiting, wakes it; or returns `msg` if the channel is
+    /// closed.
+    fn push(&self, msg: Msg<T>) -> Result<(), Msg<T>> {
+        if self.closed.load(SeqCst) {
+            return Err(msg);
+        }
+
+        if let Some(msg) = self.push_unbounded(msg)? {
+            let bounded = self.bounded.get().unwrap();
+            let mut shared = bounded.lock();
+            if self.closed.load(SeqCst) {
+                return Err(msg);
+            }
+
+            shared.queue.push_back(msg);
+        }
+
+        self.wake();
+        Ok(())
+    }
+
+    /// Queues `msg` on the lock-free queue; unless a capacity has been set, in which case `msg` is
+    /// returned to be queued on the bounded queue instead.
+    fn push_unbounded(&self, msg: Msg<T>) -> Result<Option<Msg<T>>, Msg<T>> {
+        // Announced before looking for a capacity, so that the receiver cannot take a value from
+        // the bounded queue while `msg` is still on its way onto this one, ahead of it.
+        self.pushing.fetch_add(1, SeqCst);
+
+        let pushed = match self.bounded.get() {
+            None => self.queue.send(msg).map(|_| None).map_err(|err| err.0),
+            Some(_) => Ok(Some(msg)),
+        };
+
+        self.pushing.fetch_sub(1, SeqCst);
+        pushed
+    }
+
+    /// Wakes the receiver, if it is waiting; only once for each time it has waited.
+    fn wake(&self) {
+        fence(SeqCst); // pairs with the receiver’s, so that it sees the value or is woken for it
+
+        // loaded first as, most of the time, the receiver is already busy
+        if self.waiting.load(SeqCst) && self.waiting.swap(false, SeqCst) {
+            self.waker.wake();
+        }
+    }
+
+    /// Called by the receiver after it has taken a counted value.
+    fn received(&self) {
+        self.depth.fetch_sub(1, SeqCst);
+
+        if let Some(bounded) = self.bounded.get().filter(|_| self.blocked.load(SeqCst) > 0) {
+            // Taking the lock ensures that a sender which saw a full queue is already waiting.
+            let _shared = bounded.lock();
+            bounded.space.notify_all();
+        }
+    }
+}
+
+/// The queue of a bounded channel.
+struct Bounded<T> {
+    shared: Mutex<Shared<T>>,
+    /// Signalled when a counted value is received, for senders blocked by a full queue.
+    space: Condvar,
+}
+
+impl<T> Bounded<T> {
+    fn lock(&self) -> MutexGuard<'_, Shared<T>> {
+        self.shared.lock().unwrap_or_else(|err| err.into_inner())
+    }
 }
 
 /// Shared state protected by a mutex.
-/// The receiver swaps the queue into a local buffer to reduce lock contention.
 struct Shared<T> {
     queue: VecDeque<Msg<T>>,
-    waker: Option<Waker>,
-    senders: usize,
+    bound: Option<(usize, Backpressure<T>)>,
 }
 
-impl<T> Default for Shared<T> {
-    fn default() -> Self {
-        Shared {
-            queue: Default::default(),
-            waker: Default::default(),
-            senders: 0,
+/// Stream receiver end of the channel.
+pub struct Receiver<T> {
+    channel: Arc<Channel<T>>,
+    queue: mpsc::Receiver<Msg<T>>,
+    /// The notification for the last value received, fired once the receiver has finished with it.
+    notify: Option<Notify<T>>,
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.channel.closed.store(true, SeqCst);
+
+        if let Some(notify) = self.notify.take() {
+            // unwinding, the receiver may not have finished with the value
+            notify.fire(if panicking() { Err(None) } else { Ok(()) });
+        }
+
+        // Values queued from here on are dropped along with `self.queue`.
+        let mut queued: VecDeque<_> = self.queue.try_iter().collect();
+
+        if let Some(bounded) = self.channel.bounded.get() {
+            let mut shared = bounded.lock();
+            queued.extend(take(&mut shared.queue));
+            bounded.space.notify_all(); // blocked senders will see that the channel is closed
         }
+
+        // Nothing is left to receive. Senders yet to notice that the channel is closed may still
+        // count their values, which is why `Sender::depth` ignores the counts once it is.
+        self.channel.depth.store(0, SeqCst);
+        self.channel.waited.store(0, SeqCst);
+
+        drop(queued); // the values will never be received; return those of any `sync` callers
     }
 }
 
-/// Stream receiver end of the channel.
-#[derive(Default)]
-#[pin_project] // See: https://blog.adamchalmers.com/pin-unpin/
-pub struct Receiver<T> {
-    shared: Arc<Mutex<Shared<T>>>,
-    buffer: VecDeque<Msg<T>>,
+impl<T> Receiver<T> {
+    fn pop(&self) -> Option<Msg<T>> {
+        if let Ok(msg) = self.queue.try_recv() {
+            return Some(msg);
+        }
+
+        let bounded = self.channel.bounded.get()?;
+
+        // A sender that found no capacity may still be queuing on the lock-free queue; it wakes
+        // the receiver once it has.
+        if self.channel.pushing.load(SeqCst) > 0 {
+            return None;
+        }
+
+        match self.queue.try_recv() {
+            Ok(msg) => Some(msg), // queued since the last look
+            Err(_) => bounded.lock().queue.pop_front(),
+        }
+    }
 }
 
 impl<T> Stream for Receiver<T> {
     type Item = T;
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        let inner = &mut self.project();
+        let this = self.get_mut();
 
-        loop {
-            match inner.buffer.pop_front() {
-                Some(Msg::Value(value)) => return Poll::Ready(Some(value)),
-                Some(Msg::Barrier(barrier)) => barrier.wait(),
-                _ => break,
-            };
+        // polled again, so the receiver has finished with the last value
+        if let Some(notify) = this.notify.take() {
+            notify.fire(Ok(()));
         }
 
-        let mut shared = inner.shared.lock().unwrap_or_else(|err| err.into_inner());
-        let external = &mut shared.queue;
+        let channel = &this.channel;
 
-        match external.pop_front() {
-            Some(Msg::Value(value)) => {
-                // move all other pending values (if any) into the (un-Mutex’d) internal buffer
-                swap(external, inner.buffer);
-                Poll::Ready(Some(value))
-            }
-            // A `Barrier` will always follow a `Value` and thus should have
-            // been moved into the buffer during the swap above.
-            Some(Msg::Barrier(_)) => unreachable!(),
-            None if shared.senders == 0 => Poll::Ready(None), // no receivers remaining
+        let msg = match this.pop() {
+            Some(msg) => msg,
             None => {
-                match shared.waker.as_mut() {
-                    None => shared.waker = Some(cx.waker().clone()),
-                    Some(waker) => waker.clone_from(cx.waker()),
-                };
+                channel.waker.register(cx.waker());
+                channel.waiting.store(true, SeqCst);
+                fence(SeqCst);
 
-                Poll::Pending
+                // a value may have been queued before the waker was registered
+                match this.pop() {
+                    Some(msg) => {
+                        channel.waiting.store(false, SeqCst); // no longer waiting after all
+                        msg
+                    }
+                    // every value was queued before the last sender was dropped
+                    None if channel.senders.load(SeqCst) == 0 => match this.pop() {
+                        Some(msg) => msg,
+                        None => return Poll::Ready(None), // no senders remaining
+                    },
+                    None => return Poll::Pending,
+                }
             }
-        }
+        };
+
+        Poll::Ready(Some(match msg {
+            Msg::Value(value) => value,
+            Msg::Counted(value) => {
+                channel.received();
+                value
+            }
+            Msg::Waited(mut waited) => {
+                channel.waited.fetch_sub(1, SeqCst);
+                this.notify = waited.notify.take(); // fired on the next poll
+                waited.value.take().unwrap()
+            }
+        }))
     }
 }
 
 /// Sender end of the channel.
 pub struct Sender<T> {
-    shared: Arc<Mutex<Shared<T>>>,
+    channel: Arc<Channel<T>>,
 }
 
 impl<T> Clone for Sender<T> {
     fn clone(&self) -> Self {
-        let mut shared = self.shared.lock().unwrap();
-        shared.senders += 1;
-        drop(shared);
+        self.channel.senders.fetch_add(1, SeqCst);
 
         Sender {
-            shared: self.shared.clone(),
+            channel: self.channel.clone(),
         }
     }
 }
 
 impl<T> Drop for Sender<T> {
     fn drop(&mut self) {
-        self.wake_after(|mut shared| {
-            shared.senders -= 1;
-        })
+        if self.channel.senders.fetch_sub(1, SeqCst) == 1 {
+            self.channel.wake(); // the receiver’s stream has ended
+        }
     }
 }
 
 impl<T> Sender<T> {
     /// Enqueue a value for the receiver to observe.
+    ///
+    /// The value does not count towards the channel’s capacity.
     pub fn send(&self, value: T) {
-        self.wake_after(move |mut shared| shared.queue.push_back(Msg::Value(value)))
+        self.channel.push(Msg::Value(value)).ok();
+    }
+
+    /// Enqueue a counted value, applying the channel’s [`Backpressure`] policy if it is full.
+    pub fn send_bounded(&self, value: T) {
+        self.enqueue(value, true).ok();
+    }
+
+    /// Enqueue a counted value without blocking.
+    ///
+    /// Returns the value if the channel is closed, or if it is full and its policy would block,
+    /// or drop the value.
+    pub fn try_send_bounded(&self, value: T) -> Result<(), SendError<T>> {
+        self.enqueue(value, false)
+    }
+
+    /// Sets the channel’s capacity, and the policy applied when it is reached.
+    ///
+    /// From then on, values are queued behind a `Mutex`; see the [module docs](self).
+    pub fn set_bound(&self, capacity: usize, policy: Backpressure<T>) {
+        assert!(
+            capacity > 0,
+            "a bounded channel needs a capacity of at least one"
+        );
+
+        let bounded = self.channel.bounded.get_or_init(|| Bounded {
+            shared: Mutex::new(Shared {
+                queue: Default::default(),
+                bound: None,
+            }),
+            space: Condvar::new(),
+        });
+
+        let mut shared = bounded.lock();
+        shared.bound = Some((capacity, policy));
+        bounded.space.notify_all(); // blocked senders may now have room
+    }
+
+    /// Returns `true` once the receiver has been dropped.
+    pub fn is_closed(&self) -> bool {
+        self.channel.closed.load(SeqCst)
+    }
+
+    /// Returns `true` if called on the receiver’s own thread; where blocking until the receiver
+    /// has advanced would wait forever.
+    pub fn on_receiver_thread(&self) -> bool {
+        self.channel.receiver.get() == Some(&current().id())
+    }
+
+    /// The number of counted values that have been sent but not yet received.
+    ///
+    /// Zero once the channel is closed; even while a sender has yet to find out.
+    pub fn depth(&self) -> usize {
+        match self.is_closed() {
+            true => 0,
+            false => self.channel.depth.load(SeqCst),
+        }
+    }
+
+    /// The number of counted values, and values sent with [`sync`][`Sender::sync`] or
+    /// [`send_and_wait`][`Sender::send_and_wait`], that have been sent but not yet received.
+    ///
+    /// Zero once the channel is closed, like [`depth`][`Sender::depth`].
+    pub fn pending(&self) -> usize {
+        match self.is_closed() {
+            true => 0,
+            false => self.channel.depth.load(SeqCst) + self.channel.waited.load(SeqCst),
+        }
+    }
+
+    /// Queues a value whose sender waits for the receiver to finish with it; or returns the value
+    /// if the channel is closed.
+    fn enqueue_waited(&self, value: T, notify: Notify<T>) -> Result<(), T> {
+        let channel = &*self.channel;
+
+        // counted before it is queued, so that receiving it cannot take the count below zero
+        channel.waited.fetch_add(1, SeqCst);
+
+        channel
+            .push(Msg::Waited(Waited::new(value, notify)))
+            .map_err(|msg| {
+                channel.waited.fetch_sub(1, SeqCst);
+                msg.into_waited()
+            })
+    }
+
+    fn enqueue(&self, value: T, block: bool) -> Result<(), SendError<T>> {
+        let channel = &*self.channel;
+
+        let Some(bounded) = channel.bounded.get() else {
+            // counted before it is queued, so that receiving it cannot take the depth below zero
+            channel.depth.fetch_add(1, SeqCst);
+
+            return channel.push(Msg::Counted(value)).map_err(|msg| {
+                channel.depth.fetch_sub(1, SeqCst);
+                match msg {
+                    Msg::Counted(value) => SendError::Disconnected(value),
+                    _ => unreachable!(),
+                }
+            });
+        };
+
+        let mut shared = bounded.lock();
+
+        loop {
+            if channel.closed.load(SeqCst) {
+                return Err(SendError::Disconnected(value));
+            }
+
+            let Some((capacity, policy)) = &shared.bound else {
+                break;
+            };
+
+            if let Backpressure::Coalesce(same) = policy {
+                let queued = shared.queue.iter().rposition(|msg| match msg {
+                    Msg::Counted(queued) => same(queued, &value),
+                    _ => false,
+                });
+
+                if let Some(index) = queued {
+                    shared.queue[index] = Msg::Counted(value);
+                    return Ok(()); // the receiver has already been woken for the queued value
+                }
+            }
+
+            // Register as blocked *before* checking the depth, so that a concurrent `received`
+            // either sees this sender or has already made room.
+            channel.blocked.fetch_add(1, SeqCst);
+            if channel.depth.load(SeqCst) < *capacity {
+                channel.blocked.fetch_sub(1, SeqCst);
+                break;
+            }
+
+            match policy {
+                Backpressure::Block if block => {
+                    shared = bounded
+                        .space
+                        .wait(shared)
+                        .unwrap_or_else(|err| err.into_inner());
+                    channel.blocked.fetch_sub(1, SeqCst);
+                }
+                Backpressure::Block | Backpressure::DropNewest => {
+                    channel.blocked.fetch_sub(1, SeqCst);
+                    return Err(SendError::Full(value));
+                }
+                Backpressure::DropOldest | Backpressure::Coalesce(_) => {
+                    channel.blocked.fetch_sub(1, SeqCst);
+
+                    let oldest = shared
+                        .queue
+                        .iter()
+                        .position(|msg| matches!(msg, Msg::Counted(_)));
+
+                    // Values queued before the capacity was set cannot be dropped.
+                    let Some(index) = oldest else {
+                        return Err(SendError::Full(value));
+                    };
+
+                    shared.queue.remove(index);
+                    channel.depth.fetch_sub(1, SeqCst);
+                    break;
+                }
+            }
+        }
+
+        channel.depth.fetch_add(1, SeqCst);
+        shared.queue.push_back(Msg::Counted(value));
+        drop(shared);
+
+        channel.wake();
+        Ok(())
     }
 
     /// Enqueue a value and block until the receiver has advanced past it.
     ///
-    /// This is implemented by enqueueing the value followed by a barrier. The receiver yields the
-    /// value first, then (on its next poll) observes the barrier and waits on it—unblocking the
-    /// sender.
+    /// This is implemented by enqueueing the value followed by a one-shot notification. The
+    /// receiver yields the value first, then (on its next poll) fires the notification—unblocking
+    /// the sender, without ever waiting for it.
     ///
     /// In the `Store` runtime, this means `sync` returns once the runtime has finished processing
     /// the action and returned to awaiting the next action (including draining any synchronous
     /// follow-up effects emitted during that processing).
-    pub fn sync(&self, value: T) {
-        let barrier = Arc::new(Barrier::new(2));
+    ///
+    /// Returns [`SendError::Disconnected`] if the channel is closed, or the receiver is dropped
+    /// before receiving the value; [`SendError::Panicked`] if the receiver’s thread panics before
+    /// it has advanced past the value; and [`SendError::Reentrant`] if called on the receiver’s
+    /// own thread, which could never advance past the value while blocked.
+    pub fn sync(&self, value: T) -> Result<(), SendError<T>> {
+        if self.is_closed() {
+            return Err(SendError::Disconnected(value));
+        }
 
-        self.wake_after(|mut shared| {
-            shared.queue.push_back(Msg::Value(value));
-            shared.queue.push_back(Msg::Barrier(barrier.clone()));
-        });
+        if self.on_receiver_thread() {
+            return Err(SendError::Reentrant(value));
+        }
 
-        barrier.wait();
-    }
+        // Not a future: this thread may already be running an executor, which cannot be re-entered.
+        let (notify, notified) = mpsc::sync_channel(1);
 
-    /// Downgrade to a weak sender for use by long-lived tasks. If the receiver has been dropped,
-    /// upgrading will fail.
-    pub fn downgrade(&self) -> WeakSender<T> {
-        WeakSender {
-            shared: Arc::downgrade(&self.shared),
+        if let Err(value) = self.enqueue_waited(value, Notify::Thread(notify)) {
+            return Err(SendError::Disconnected(value));
         }
+
+        Notify::result(notified.recv())
     }
 
-    /// Perform some work and then, if a `Receiver` was waiting, wake it.
+    /// Enqueue a value and return a future that resolves once the receiver has advanced past it.
+    ///
+    /// This is the non-blocking equivalent of [`sync`][`Sender::sync`], which may also be awaited
+    /// on the receiver’s own thread.
     ///
-    /// Note that the [`Waker`] will only ever be called once for each time it
-    /// has entered the [`Poll::Pending`] state. Regardless of how many times
-    /// `wake_after` is called.
-    fn wake_after<F: FnOnce(MutexGuard<Shared<T>>)>(&self, f: F) {
-        let mut shared = self
-            .shared
-            .lock() //
-            .unwrap_or_else(|err| err.into_inner());
+    /// The value is enqueued immediately, not when the future is first polled. The future
+    /// resolves to the same errors as `sync`, bar [`SendError::Reentrant`].
+    pub fn send_and_wait(
+        &self,
+        value: T,
+    ) -> impl Future<Output = Result<(), SendError<T>>> + Send + 'static
+    where
+        T: Send + 'static,
+    {
+        let (notify, notified) = oneshot::channel();
 
-        let waker = shared.waker.take(); // there are no “extra” wakes
-        f(shared);
+        let rejected = self.enqueue_waited(value, Notify::Future(notify)).err();
+
+        async move {
+            if let Some(value) = rejected {
+                return Err(SendError::Disconnected(value));
+            }
+
+            Notify::result(notified.await)
+        }
+    }
 
-        if let Some(waker) = waker {
-            waker.wake() // wake _after_ the `MutexGuard` has been dropped by `f(…)`
+    /// Downgrade to a weak sender for use by long-lived tasks. If the receiver has been dropped,
+    /// upgrading will fail.
+    pub fn downgrade(&self) -> WeakSender<T> {
+        WeakSender {
+            channel: Arc::downgrade(&self.channel),
         }
     }
 }
 
 /// Weak sender handle (used by store tasks).
 pub struct WeakSender<T> {
-    shared: Weak<Mutex<Shared<T>>>,
+    channel: Weak<Channel<T>>,
 }
 
 impl<T> Clone for WeakSender<T> {
     fn clone(&self) -> Self {
         WeakSender {
-            shared: self.shared.clone(),
+            channel: self.channel.clone(),
         }
     }
 }
 
 impl<T> WeakSender<T> {
     pub fn upgrade(&self) -> Option<Sender<T>> {
-        self.shared.upgrade().map(|shared| {
-            shared.lock().unwrap().senders += 1;
-            Sender { shared }
+        self.channel.upgrade().map(|channel| {
+            channel.senders.fetch_add(1, SeqCst);
+            Sender { channel }
         })
     }
 }
 
 /// Weak receiver handle returned by `channel()` to avoid keeping the channel alive accidentally.
 pub struct WeakReceiver<T> {
-    shared: Weak<Mutex<Shared<T>>>,
+    channel: Weak<Channel<T>>,
+    queue: mpsc::Receiver<Msg<T>>,
 }
 
 impl<T> WeakReceiver<T> {
     pub fn upgrade(self) -> Option<Receiver<T>> {
-        self.shared
-            .upgrade() //
-            .map(|shared| Receiver {
-                shared,
-                buffer: Default::default(),
-            })
+        let queue = self.queue;
+
+        self.channel.upgrade().map(|channel| {
+            channel.receiver.set(current().id()).ok();
+            Receiver {
+                channel,
+                queue,
+                notify: None,
+            }
+        })
     }
 }
 
 /// Create a new channel pair.
 pub fn channel<T>() -> (Sender<T>, WeakReceiver<T>) {
-    let shared = Shared {
-        senders: 1,
-        ..Default::default()
-    };
-    let shared = Arc::new(Mutex::new(shared));
+    let (queue, receiver) = mpsc::channel();
+    let channel = Arc::new(Channel {
+        queue,
+        bounded: OnceLock::new(),
+        waker: AtomicWaker::new(),
+        waiting: AtomicBool::new(false),
+        senders: AtomicUsize::new(1),
+        closed: AtomicBool::new(false),
+        depth: AtomicUsize::new(0),
+        waited: AtomicUsize::new(0),
+        blocked: AtomicUsize::new(0),
+        pushing: AtomicUsize::new(0),
+        receiver: OnceLock::new(),
+    });
 
     let recv = WeakReceiver {
-        shared: Arc::downgrade(&shared),
+        channel: Arc::downgrade(&channel),
+        queue: receiver,
     };
-    let send = Sender { shared };
+    let send = Sender { channel };
 
     (send, recv)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use futures::executor::block_on_stream;
+
+    #[cfg(not(miri))]
+    use ntest_timeout::timeout;
+
+    use super::*;
+
+    fn bounded(capacity: usize, policy: Backpressure<u32>) -> (Sender<u32>, Receiver<u32>) {
+        let (sender, receiver) = channel();
+        sender.set_bound(capacity, policy);
+
+        (sender, receiver.upgrade().unwrap())
+    }
+
+    fn drain(sender: Sender<u32>, receiver: Receiver<u32>) -> Vec<u32> {
+        drop(sender);
+        block_on_stream(receiver).collect()
+    }
+
+    #[test]
+    fn test_drop_newest() {
+        let (sender, receiver) = bounded(2, Backpressure::DropNewest);
+
+        sender.send_bounded(1);
+        sender.send_bounded(2);
+        sender.send_bounded(3);
+        assert_eq!(sender.try_send_bounded(4), Err(SendError::Full(4)));
+        sender.send(5); // uncounted values are always accepted
+        assert_eq!(sender.depth(), 2);
+
+        assert_eq!(drain(sender, receiver), [1, 2, 5]);
+    }
+
+    #[test]
+    fn test_drop_oldest() {
+        let (sender, receiver) = bounded(2, Backpressure::DropOldest);
+
+        sender.send(0);
+        sender.send_bounded(1);
+        sender.send_bounded(2);
+        sender.send_bounded(3);
+        assert_eq!(sender.try_send_bounded(4), Ok(()));
+
+        assert_eq!(drain(sender, receiver), [0, 3, 4]);
+    }
+
+    #[test]
+    fn test_coalesce() {
+        let (sender, receiver) = bounded(3, Backpressure::coalesce_by(|n| n % 10));
+
+        sender.send_bounded(1);
+        sender.send_bounded(2);
+        sender.send_bounded(11); // replaces 1
+        sender.send_bounded(3);
+        sender.send_bounded(4); // full and no match; drops the oldest
+        assert_eq!(sender.depth(), 3);
+
+        assert_eq!(drain(sender, receiver), [2, 3, 4]);
+    }
+
+    #[test]
+    #[cfg(not(miri))]
+    #[timeout(10000)]
+    fn test_fifo_across_senders() {
+        let (sender, receiver) = channel::<(usize, u32)>();
+        let receiver = receiver.upgrade().unwrap();
+
+        thread::scope(|scope| {
+            for id in 0..4 {
+                let sender = sender.clone();
+                scope.spawn(move || (0..1000).for_each(|n| sender.send((id, n))));
+            }
+        });
+
+        drop(sender);
+        let mut next = [0; 4];
+
+        for (id, n) in block_on_stream(receiver) {
+            assert_eq!(n, next[id]); // each sender’s values arrive in the order they were sent
+            next[id] += 1;
+        }
+
+        assert_eq!(next, [1000; 4]);
+    }
+
+    #[test]
+    fn test_fifo_while_a_capacity_is_set() {
+        let (sender, receiver) = channel();
+        let receiver = receiver.upgrade().unwrap();
+
+        // a sender that found no capacity, and has yet to queue `1`
+        sender.channel.pushing.fetch_add(1, SeqCst);
+
+        sender.set_bound(2, Backpressure::Block);
+        sender.send_bounded(2);
+        assert!(receiver.pop().is_none()); // waiting for the sender still queuing `1`
+
+        sender.channel.queue.send(Msg::Value(1)).unwrap();
+        sender.channel.pushing.fetch_sub(1, SeqCst);
+
+        assert_eq!(drain(sender, receiver), [1, 2]);
+    }
+
+    #[test]
+    fn test_dropping_the_receiver_clears_the_depth() {
+        let (sender, receiver) = channel();
+        let receiver = receiver.upgrade().unwrap();
+
+        sender.send_bounded(1);
+        sender.send_bounded(2);
+        assert_eq!(sender.depth(), 2);
+
+        drop(receiver);
+        assert_eq!(sender.depth(), 0);
+        assert_eq!(sender.pending(), 0);
+    }
+
+    #[test]
+    #[cfg(not(miri))]
+    #[timeout(10000)]
+    fn test_ends_once_every_sender_is_dropped() {
+        let (sender, receiver) = channel();
+        let mut receiver = block_on_stream(receiver.upgrade().unwrap());
+
+        let weak = sender.downgrade();
+        let other = weak.upgrade().unwrap();
+        drop(sender);
+
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            other.send(1);
+        });
+
+        assert_eq!(receiver.next(), Some(1)); // the stream waits for the remaining sender
+        handle.join().unwrap();
+        assert_eq!(receiver.next(), None);
+    }
+
+    #[test]
+    #[cfg(not(miri))]
+    #[timeout(10000)]
+    fn test_sync_waits_for_the_receiver() {
+        let (sender, receiver) = channel();
+        let mut receiver = block_on_stream(receiver.upgrade().unwrap());
+
+        let synced = sender.clone();
+        let handle = thread::spawn(move || synced.sync(1));
+        drop(sender);
+
+        assert_eq!(receiver.next(), Some(1));
+        assert_eq!(receiver.next(), None); // firing the notification on the way
+        assert_eq!(handle.join().unwrap(), Ok(()));
+    }
+
+    #[test]
+    fn test_sync_on_the_receivers_thread_is_an_error() {
+        let (sender, receiver) = channel();
+        let mut receiver = block_on_stream(receiver.upgrade().unwrap());
+
+        assert_eq!(sender.sync(1), Err(SendError::Reentrant(1)));
+
+        sender.send(2);
+        drop(sender);
+        assert_eq!(receiver.next(), Some(2)); // `1` was never sent
+        assert_eq!(receiver.next(), None);
+    }
+
+    #[test]
+    #[cfg(not(miri))]
+    #[timeout(10000)]
+    fn test_dropping_the_receiver_releases_sync() {
+        let (sender, receiver) = channel();
+        let receiver = receiver.upgrade().unwrap();
+
+        let synced = sender.clone();
+        let handle = thread::spawn(move || synced.sync(1));
+
+        thread::sleep(Duration::from_millis(10));
+        drop(receiver); // without ever receiving the value
+        assert_eq!(handle.join().unwrap(), Err(SendError::Disconnected(1)));
+
+        assert!(sender.is_closed());
+        assert_eq!(sender.sync(2), Err(SendError::Disconnected(2))); // returns at once
+    }
+
+    #[test]
+    #[cfg(not(miri))]
+    #[timeout(10000)]
+    fn test_block() {
+        let (sender, receiver) = bounded(1, Backpressure::Block);
+        let mut receiver = block_on_stream(receiver);
+
+        sender.send_bounded(1);
+        assert_eq!(sender.try_send_bounded(2), Err(SendError::Full(2)));
+
+        let blocked = sender.clone();
+        let handle = thread::spawn(move || blocked.send_bounded(3));
+
+        thread::sleep(Duration::from_millis(10));
+        assert_eq!(sender.depth(), 1);
+        assert_eq!(receiver.next(), Some(1));
+
+        handle.join().unwrap();
+        assert_eq!(receiver.next(), Some(3));
+        assert_eq!(sender.depth(), 0);
+    }
+}
@@ -0,0 +1,103 @@
+//! Errors returned by a `Store`.
+
+use std::any::Any;
+use std::error::Error;
+use std::fmt::{self, Debug, Display, Formatter};
+
+/// The error returned by [`Store::try_send`][`crate::Store::try_send`],
+/// [`Store::sync`][`crate::Store::sync`] and [`Store::send_and_wait`][`crate::Store::send_and_wait`].
+///
+/// [`Store::with_state`][`crate::Store::with_state`] returns it without an action.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SendError<Action> {
+    /// The `Store`’s queue is full; the action was not sent.
+    Full(Action),
+    /// The `Store`’s runtime has stopped; the action was not sent.
+    Disconnected(Action),
+    /// The action was sent from the `Store`’s own runtime thread, which would have waited for
+    /// itself forever; the action was not sent.
+    Reentrant(Action),
+    /// The `Store`’s runtime panicked, and stopped, before it had finished processing the action.
+    Panicked,
+}
+
+impl<Action> SendError<Action> {
+    /// Returns the action that could not be sent; or `None` if it was [`Panicked`] on.
+    ///
+    /// [`Panicked`]: SendError::Panicked
+    pub fn into_inner(self) -> Option<Action> {
+        match self {
+            SendError::Full(action)
+            | SendError::Disconnected(action)
+            | SendError::Reentrant(action) => Some(action),
+            SendError::Panicked => None,
+        }
+    }
+
+    /// Converts the error to one for the value returned by `f`.
+    pub(crate) fn map<T>(self, f: impl FnOnce(Action) -> T) -> SendError<T> {
+        match self {
+            SendError::Full(action) => SendError::Full(f(action)),
+            SendError::Disconnected(action) => SendError::Disconnected(f(action)),
+            SendError::Reentrant(action) => SendError::Reentrant(f(action)),
+            SendError::Panicked => SendError::Panicked,
+        }
+    }
+}
+
+impl<Action> Debug for SendError<Action> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SendError::Full(_) => f.write_str("Full(..)"),
+            SendError::Disconnected(_) => f.write_str("Disconnected(..)"),
+            SendError::Reentrant(_) => f.write_str("Reentrant(..)"),
+            SendError::Panicked => f.write_str("Panicked"),
+        }
+    }
+}
+
+impl<Action> Display for SendError<Action> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SendError::Full(_) => f.write_str("the store’s queue is full"),
+            SendError::Disconnected(_) => f.write_str("the store’s runtime has stopped"),
+            SendError::Reentrant(_) => f.write_str("the store’s runtime cannot wait for itself"),
+            SendError::Panicked => f.write_str("the store’s runtime panicked"),
+        }
+    }
+}
+
+impl<Action> Error for SendError<Action> {}
+
+/// The error returned by [`Store::into_inner`][`crate::Store::into_inner`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StoreError {
+    /// The `Store`’s runtime thread panicked, with the given message.
+    ///
+    /// See [`PanicPolicy`][`crate::PanicPolicy`] for the reducer panics that a `Store` can recover from.
+    Panicked(String),
+}
+
+impl StoreError {
+    pub(crate) fn panicked(payload: Box<dyn Any + Send>) -> Self {
+        let message = match payload.downcast::<String>() {
+            Ok(message) => *message,
+            Err(payload) => match payload.downcast::<&'static str>() {
+                Ok(message) => message.to_string(),
+                Err(_) => String::from("Box<dyn Any>"),
+            },
+        };
+
+        StoreError::Panicked(message)
+    }
+}
+
+impl Display for StoreError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::Panicked(message) => write!(f, "the store’s runtime panicked: {message}"),
+        }
+    }
+}
+
+impl Error for StoreError {}
@@ -0,0 +1,278 @@
+//! `LocalStore`: a store driven by its caller rather than by a dedicated thread.
+//!
+//! A [`Store`](crate::Store) runs its reducer on a thread of its own, so every interaction crosses a
+//! thread boundary. Game loops and immediate-mode UIs already have a loop of their own, and would
+//! rather pump the store once per frame and then read its state directly.
+//!
+//! [`LocalStore`] owns the same runtime as a `Store` (along with its local executor and action
+//! queue) but keeps it on the caller’s thread. Nothing happens until the caller
+//! [`step`][`LocalStore::step`]s it.
+//!
+//! The ordering guarantees are those of a `Store`: synchronous follow-up actions are drained before
+//! the next queued action is processed.
+
+use std::task::{Context, Poll};
+
+use futures::executor::LocalPool;
+use futures::task::noop_waker_ref;
+use futures::StreamExt;
+
+use crate::dependencies::with_dependencies;
+use crate::effects::{cancellation::Cancellations, Executor, Spawner};
+use crate::reducer::Reducer;
+use crate::store::channel::{channel, Receiver, Sender};
+use crate::store::middleware::Middleware;
+use crate::store::recording::Origin;
+use crate::store::runtime::{PanicPolicy, Runtime};
+use crate::store::Message;
+
+/// A [`Store`](crate::Store) that runs on the caller’s thread, one [`step`][`LocalStore::step`] at a time.
+///
+/// ```rust
+/// # use composable::*;
+/// #[derive(Default)]
+/// struct State(usize);
+///
+/// #[derive(Clone, Debug)]
+/// enum Action {
+///     Increment,
+///     Load,
+/// }
+///
+/// impl Reducer for State {
+///     type Action = Action;
+///     type Output = usize;
+///
+///     fn reduce(&mut self, action: Action, send: impl Effects<Action>) {
+///         match action {
+///             Action::Increment => self.0 += 1,
+///             Action::Load => send.future(async { Some(Action::Increment) }),
+///         }
+///     }
+/// }
+///
+/// let mut store = LocalStore::<State>::default();
+///
+/// // each frame…
+/// store.send(Action::Load);
+/// store.run_until_stalled();
+/// assert_eq!(store.state().0, 1); // …render from the state
+/// ```
+///
+/// # Dependencies
+/// As the `LocalStore` runs on the caller’s thread, any dependencies its reducer needs are those in
+/// scope when it is stepped; see [`with_dependencies`][`crate::dependencies::with_dependencies`].
+pub struct LocalStore<State: Reducer> {
+    runtime: Runtime<State>,
+    pool: LocalPool,
+    sender: Sender<Message<<State as Reducer>::Action>>,
+    receiver: Receiver<Message<<State as Reducer>::Action>>,
+    cancellations: Cancellations,
+}
+
+impl<State: Reducer> Default for LocalStore<State>
+where
+    State: Default,
+    <State as Reducer>::Action: 'static,
+{
+    /// Creates a new `LocalStore` with a default initial state.
+    fn default() -> Self {
+        Self::with_initial(State::default())
+    }
+}
+
+impl<State: Reducer> LocalStore<State>
+where
+    <State as Reducer>::Action: 'static,
+{
+    /// Creates a new `LocalStore` with `state` as its initial state.
+    ///
+    /// Unlike [`Store::with_initial`][`crate::Store::with_initial`], `state` does not need to be [`Send`].
+    pub fn with_initial(state: State) -> Self {
+        let (sender, receiver) = channel();
+
+        Self {
+            runtime: Runtime::new(state),
+            pool: LocalPool::new(),
+            receiver: receiver.upgrade().unwrap(),
+            cancellations: Default::default(),
+            sender,
+        }
+    }
+
+    /// Queues `action` to be processed by the next [`step`][`LocalStore::step`].
+    ///
+    /// Takes an [`Into<Action>`] so that both child and parent `Action`s may be sent easily.
+    pub fn send(&self, action: impl Into<<State as Reducer>::Action>) {
+        // counted, so that `Background` tasks wait for it
+        self.sender.send_bounded(Message::Action(action.into()))
+    }
+
+    /// Runs the `LocalStore`’s effects until none of them can make progress, then processes every
+    /// queued action, along with any synchronous follow-up actions.
+    ///
+    /// Effects started by those actions run on the next `step`, so a frame can render from the
+    /// [`state`][`LocalStore::state`] as soon as this returns.
+    ///
+    /// Returns `true` if any actions were processed.
+    ///
+    /// # Panics
+    /// If the reducer panics and the [`PanicPolicy`] is to stop, which is the default.
+    pub fn step(&mut self) -> bool {
+        let spawner = Spawner::new(Executor::spawner(&self.pool), self.sender.downgrade());
+        let cancellations = self.cancellations.clone();
+
+        with_dependencies((spawner, cancellations), || {
+            self.pool.run_until_stalled();
+
+            // Effects wake the `Receiver` as they send; but it is only ever polled from here.
+            let mut cx = Context::from_waker(noop_waker_ref());
+            let mut processed = false;
+
+            while let Poll::Ready(Some(message)) = self.receiver.poll_next_unpin(&mut cx) {
+                match message {
+                    Message::Action(action) => self.runtime.reduce(action, Origin::External),
+                    Message::Effect(action) => self.runtime.reduce(action, Origin::Effect),
+                    Message::Runtime(_) | Message::Shutdown(_) => {
+                        unreachable!("only actions are sent to a `LocalStore`")
+                    }
+                }
+
+                processed = true;
+            }
+
+            processed
+        })
+    }
+
+    /// Calls [`step`][`LocalStore::step`] until there are no more actions to process and none of
+    /// the `LocalStore`’s effects can make progress.
+    ///
+    /// Effects waiting on time, or on another thread, are left waiting.
+    ///
+    /// # Warning
+    /// An effect that never stops sending actions, without waiting in between, will prevent this
+    /// method from returning.
+    pub fn run_until_stalled(&mut self) {
+        while self.step() {}
+    }
+
+    /// Returns the `LocalStore`’s current state.
+    pub fn state(&self) -> &State {
+        self.runtime.state()
+    }
+
+    /// Adds `middleware` to the end of the `LocalStore`’s [`Middleware`] chain.
+    pub fn add_middleware(&mut self, middleware: impl Middleware<State> + 'static) {
+        self.runtime.add_middleware(Box::new(middleware));
+    }
+
+    /// Sets what the `LocalStore` does when its [`Reducer`] panics; see [`PanicPolicy`].
+    ///
+    /// Rather than stopping a runtime thread, [`PanicPolicy::stop`] lets the panic continue out of
+    /// [`step`][`LocalStore::step`].
+    pub fn on_panic(mut self, policy: PanicPolicy<State>) -> Self {
+        self.runtime.on_panic(policy);
+        self
+    }
+
+    /// Consumes the `LocalStore` and returns its current `state` value.
+    ///
+    /// Queued actions and unfinished effects are dropped; call
+    /// [`run_until_stalled`][`LocalStore::run_until_stalled`] first if they matter.
+    pub fn into_inner(self) -> <State as Reducer>::Output
+    where
+        State: Into<<State as Reducer>::Output>,
+    {
+        self.runtime.into_inner().into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use futures::channel::oneshot;
+
+    use crate::Effects;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct State {
+        log: Vec<&'static str>,
+        pending: Rc<Cell<Option<oneshot::Receiver<()>>>>, // not `Send`
+    }
+
+    #[derive(Debug)]
+    enum Action {
+        Start,
+        Started,
+        Wait,
+        Done,
+    }
+
+    impl Reducer for State {
+        type Action = Action;
+        type Output = Vec<&'static str>;
+
+        fn reduce(&mut self, action: Action, send: impl Effects<Action>) {
+            self.log.push(match action {
+                Action::Start => {
+                    send.action(Action::Started);
+                    "start"
+                }
+                Action::Started => "started",
+                Action::Wait => {
+                    let pending = self.pending.take().unwrap();
+                    send.future(async move {
+                        pending.await.ok();
+                        Some(Action::Done)
+                    });
+                    "wait"
+                }
+                Action::Done => "done",
+            });
+        }
+    }
+
+    impl From<State> for Vec<&'static str> {
+        fn from(value: State) -> Self {
+            value.log
+        }
+    }
+
+    #[test]
+    fn test_nothing_happens_until_stepped() {
+        let mut store = LocalStore::<State>::default();
+
+        store.send(Action::Start);
+        assert!(store.state().log.is_empty());
+
+        assert!(store.step());
+        assert_eq!(store.state().log, ["start", "started"]);
+
+        assert!(!store.step());
+        assert_eq!(store.into_inner(), ["start", "started"]);
+    }
+
+    #[test]
+    fn test_effects_run_on_later_steps() {
+        let (done, pending) = oneshot::channel();
+        let mut store = LocalStore::with_initial(State {
+            pending: Rc::new(Cell::new(Some(pending))),
+            ..Default::default()
+        });
+
+        store.send(Action::Wait);
+        store.run_until_stalled();
+        assert_eq!(store.state().log, ["wait"]);
+
+        assert!(!store.step()); // the effect is still waiting
+
+        done.send(()).unwrap();
+        assert!(store.step());
+        assert_eq!(store.state().log, ["wait", "done"]);
+    }
+}
@@ -0,0 +1,322 @@
+//! Middleware: cross-cutting concerns around every call to a [`Reducer`].
+//!
+//! Both [`Store`](crate::Store) and [`TestStore`](crate::TestStore) keep a chain of
+//! [`Middleware`] that wraps every `reduce` call; external actions and synchronous follow-up
+//! actions alike.
+//!
+//! - [`before`][`Middleware::before`] hooks run in the order the middleware were added, each
+//!   receiving the action returned by the previous one.
+//! - [`after`][`Middleware::after`] hooks run in the reverse order, once the reducer has updated
+//!   the state.
+//!
+//! If any `before` hook drops the action, the reducer is not called and no `after` hooks run.
+//!
+//! Both hooks are also given a [`Dispatch`] handle, through which they can send further actions
+//! to the store; to re-route an action to another part of the state, for example. These are
+//! queued as synchronous follow-up actions, just as those a reducer sends with
+//! [`Effects::action`] are, and pass through the whole chain again.
+
+use crate::reducer::Reducer;
+use crate::Effects;
+
+/// Sends actions from a [`Middleware`] back into the `Store`, as synchronous follow-up actions.
+pub struct Dispatch<'a, Action> {
+    send: &'a dyn Fn(Action),
+}
+
+impl<Action> Dispatch<'_, Action> {
+    /// Sends `action` through the `Store`’s [`Reducer`], once the current action has been
+    /// processed.
+    #[doc(alias = "send")]
+    pub fn action(&self, action: impl Into<Action>) {
+        (self.send)(action.into())
+    }
+}
+
+/// `Middleware` sees every action before and after a `Store`’s [`Reducer`] is called with it.
+///
+/// Logging, analytics and guards can be written once as middleware rather than being repeated
+/// in each reducer.
+///
+/// ```rust
+/// # use composable::*;
+/// # #[derive(Clone, Debug, Default, PartialEq)]
+/// # struct State { n: usize }
+/// # #[derive(Clone, Debug, PartialEq)]
+/// # enum Action { Increment, Reset, Undo }
+/// # impl Reducer for State {
+/// #     type Action = Action;
+/// #     type Output = Self;
+/// #     fn reduce(&mut self, action: Action, _send: impl Effects<Action>) {
+/// #         match action {
+/// #             Action::Increment => self.n += 1,
+/// #             Action::Reset | Action::Undo => self.n = 0,
+/// #         }
+/// #     }
+/// # }
+/// /// Ignores `Reset`, re-routes `Undo` as a `Reset`, and logs every other action.
+/// struct Guard;
+///
+/// impl Middleware<State> for Guard {
+///     fn before(
+///         &mut self,
+///         action: Action,
+///         _state: &State,
+///         send: &Dispatch<Action>,
+///     ) -> Option<Action> {
+///         match action {
+///             Action::Reset => None,
+///             Action::Undo => {
+///                 send.action(Action::Reset);
+///                 None
+///             }
+///             action => {
+///                 println!("{action:?}");
+///                 Some(action)
+///             }
+///         }
+///     }
+/// }
+///
+/// let mut store = TestStore::<State>::default();
+/// store.add_middleware(Guard);
+///
+/// store.send(Action::Increment, |state| state.n = 1);
+/// store.send(Action::Reset, |_| {});
+/// store.send(Action::Undo, |_| {});
+/// store.recv(Action::Reset, |_| {});
+/// ```
+pub trait Middleware<State: Reducer> {
+    /// Called before the reducer with the `action` it is about to receive.
+    ///
+    /// Returns the action that should be passed on; which may be a different action entirely.
+    /// Returning `None` drops the action and the reducer will not be called.
+    ///
+    /// Actions sent through `send` are processed after this one, whether or not it is dropped.
+    fn before(
+        &mut self,
+        action: <State as Reducer>::Action,
+        state: &State,
+        send: &Dispatch<<State as Reducer>::Action>,
+    ) -> Option<<State as Reducer>::Action> {
+        let _ = (state, send);
+        Some(action)
+    }
+
+    /// Called after the reducer has processed an action, with the updated `state`.
+    ///
+    /// Actions sent through `send` are processed after those sent by the reducer.
+    fn after(&mut self, state: &State, send: &Dispatch<<State as Reducer>::Action>) {
+        let _ = (state, send);
+    }
+}
+
+/// The [`Middleware`] added to a store, in the order they were added.
+pub(crate) struct Chain<State: Reducer> {
+    middleware: Vec<Box<dyn Middleware<State>>>,
+}
+
+impl<State: Reducer> Default for Chain<State> {
+    fn default() -> Self {
+        Self {
+            middleware: Default::default(),
+        }
+    }
+}
+
+impl<State: Reducer> Chain<State> {
+    pub(crate) fn push(&mut self, middleware: Box<dyn Middleware<State>>) {
+        self.middleware.push(middleware);
+    }
+
+    /// Calls `state.reduce(…)` with `action`, passing it through every middleware on the way.
+    pub(crate) fn reduce(
+        &mut self,
+        state: &mut State,
+        action: <State as Reducer>::Action,
+        send: impl Effects<<State as Reducer>::Action>,
+    ) {
+        let effects = send.clone();
+        let dispatch = |action| effects.action(action);
+        let dispatch = Dispatch { send: &dispatch };
+
+        let mut action = Some(action);
+        for middleware in self.middleware.iter_mut() {
+            action = action.and_then(|action| middleware.before(action, state, &dispatch));
+        }
+
+        let Some(action) = action else {
+            return;
+        };
+
+        state.reduce(action, send);
+
+        for middleware in self.middleware.iter_mut().rev() {
+            middleware.after(state, &dispatch);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    #[cfg(not(miri))]
+    use ntest_timeout::timeout;
+
+    use crate::{Store, TestStore};
+
+    use super::*;
+
+    #[derive(Clone, Debug, Default, PartialEq)]
+    struct State {
+        n: usize,
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Action {
+        Add(usize),
+        Twice(usize),
+        Route(usize),
+        Added,
+    }
+
+    impl Reducer for State {
+        type Action = Action;
+        type Output = usize;
+
+        fn reduce(&mut self, action: Action, send: impl Effects<Action>) {
+            match action {
+                Action::Add(n) => self.n += n,
+                Action::Twice(n) => {
+                    send.action(Action::Add(n));
+                    send.action(Action::Add(n));
+                }
+                Action::Route(_) | Action::Added => {}
+            }
+        }
+    }
+
+    impl From<State> for usize {
+        fn from(value: State) -> Self {
+            value.n
+        }
+    }
+
+    /// Records the actions it sees, and the state after each one.
+    struct Log(Arc<Mutex<Vec<String>>>, &'static str);
+
+    impl Middleware<State> for Log {
+        fn before(&mut self, action: Action, _: &State, _: &Dispatch<Action>) -> Option<Action> {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("{} {action:?}", self.1));
+            Some(action)
+        }
+
+        fn after(&mut self, state: &State, _: &Dispatch<Action>) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("{} n = {}", self.1, state.n));
+        }
+    }
+
+    /// Drops zeroes and doubles everything else.
+    struct Double;
+
+    impl Middleware<State> for Double {
+        fn before(&mut self, action: Action, _: &State, _: &Dispatch<Action>) -> Option<Action> {
+            match action {
+                Action::Add(0) => None,
+                Action::Add(n) => Some(Action::Add(n * 2)),
+                action => Some(action),
+            }
+        }
+    }
+
+    /// Re-routes `Route` into as many `Add(1)`s, and follows each change to `n` with `Added`.
+    struct Router(usize);
+
+    impl Middleware<State> for Router {
+        fn before(&mut self, action: Action, _: &State, send: &Dispatch<Action>) -> Option<Action> {
+            match action {
+                Action::Route(n) => {
+                    (0..n).for_each(|_| send.action(Action::Add(1)));
+                    None
+                }
+                action => Some(action),
+            }
+        }
+
+        fn after(&mut self, state: &State, send: &Dispatch<Action>) {
+            if state.n != self.0 {
+                self.0 = state.n;
+                send.action(Action::Added);
+            }
+        }
+    }
+
+    #[test]
+    fn test_middleware_order_and_transformations() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        let mut store = TestStore::<State>::default();
+        store.add_middleware(Log(log.clone(), "outer"));
+        store.add_middleware(Double);
+        store.add_middleware(Log(log.clone(), "inner"));
+
+        store.send(Action::Add(1), |state| state.n = 2);
+        store.send(Action::Add(0), |_| {});
+
+        assert_eq!(
+            *log.lock().unwrap(),
+            [
+                "outer Add(1)",
+                "inner Add(2)",
+                "inner n = 2",
+                "outer n = 2",
+                "outer Add(0)",
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(not(miri))]
+    #[timeout(10000)]
+    fn test_middleware_sees_follow_up_actions() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        let store = Store::with_initial(State::default());
+        store.add_middleware(Log(log.clone(), ""));
+        store.add_middleware(Double);
+
+        store.send(Action::Twice(1));
+        assert_eq!(store.into_inner().unwrap(), 4);
+
+        assert_eq!(
+            *log.lock().unwrap(),
+            [
+                " Twice(1)",
+                " n = 0",
+                " Add(1)",
+                " n = 2",
+                " Add(1)",
+                " n = 4",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_middleware_can_reroute_actions() {
+        let mut store = TestStore::<State>::default();
+        store.add_middleware(Router(0));
+
+        store.send(Action::Route(2), |_| {});
+        store.recv(Action::Add(1), |state| state.n = 1);
+        store.recv(Action::Add(1), |state| state.n = 2);
+        store.recv(Action::Added, |_| {});
+        store.recv(Action::Added, |_| {});
+    }
+}
@@ -1,22 +1,71 @@
 //! `Store`: a runtime for reducers.
 //!
-use std::thread::{JoinHandle, Thread};
+use std::any::Any;
+use std::sync::{Arc, Mutex};
+use std::thread::{sleep, JoinHandle};
+use std::time::Instant;
 
-use crate::dependencies::Tuple;
+use futures::channel::{mpsc::unbounded, oneshot};
+use futures::executor::{block_on, LocalPool};
+use futures::{Future, FutureExt};
+
+use crate::dependencies::{Dependency, Tuple};
+use crate::effects::{scheduler::Reactor, Executor, Resume};
 use crate::Reducer;
+pub use channel::Backpressure;
 use channel::Sender;
+pub use error::{SendError, StoreError};
+pub use local::LocalStore;
+pub use middleware::{Dispatch, Middleware};
+use recording::{Entry, Origin, Recorder, Recording};
+pub use runtime::PanicPolicy;
+use runtime::Runtime;
+pub use subscription::Subscription;
 
 pub(crate) mod channel;
+mod error;
+mod local;
+mod middleware;
+pub mod recording;
 mod runtime;
+mod subscription;
 
 pub(crate) mod testing;
 
 #[doc = include_str!("README.md")]
 pub struct Store<State: Reducer> {
-    sender: Sender<Result<<State as Reducer>::Action, Thread>>,
+    sender: Sender<Message<<State as Reducer>::Action>>,
     handle: JoinHandle<<State as Reducer>::Output>,
 }
 
+/// The messages processed by a `Store`’s runtime thread.
+///
+/// Effect tasks only know the `Action` type, so anything that needs the `State` is type-erased
+/// into a [`Message::Runtime`] closure and downcast again on the runtime thread.
+pub(crate) enum Message<Action> {
+    /// An action to be reduced, sent from outside of the `Store`.
+    Action(Action),
+    /// An action to be reduced, sent by one of the `Store`’s effect tasks.
+    Effect(Action),
+    /// Work to be performed on the runtime thread with access to its [`Runtime`].
+    Runtime(Erased),
+    /// Sent by [`Store::into_inner`], which waits for the store to shut down.
+    Shutdown(oneshot::Sender<()>),
+}
+
+impl<Action> Message<Action> {
+    /// Returns the action sent by [`Store::send`], and the like.
+    fn into_action(self) -> Action {
+        match self {
+            Message::Action(action) => action,
+            _ => unreachable!("only actions are sent"),
+        }
+    }
+}
+
+/// A closure expecting a `&mut Runtime<State>` as its argument.
+pub(crate) type Erased = Box<dyn FnOnce(&mut dyn Any) + Send>;
+
 impl<State: Reducer> Store<State> {
     /// Creates a new `Store` with `state` as its initial state.
     ///
@@ -31,7 +80,7 @@ impl<State: Reducer> Store<State> {
         <State as Reducer>::Action: Send,
         <State as Reducer>::Output: Send + From<State>,
     {
-        Store::runtime(|| state, || ((),))
+        Store::runtime(|| state, LocalPool::new, || ((),))
     }
 
     /// Creates a new `Store` with its initial state generated by functions and a second function that
@@ -43,13 +92,14 @@ impl<State: Reducer> Store<State> {
     /// without relying on global state.
     pub fn with_dependencies<F, D, T>(with: F, dependencies: D) -> Self
     where
+        State: 'static,
         F: (FnOnce() -> State) + Send + 'static,
         D: (FnOnce() -> T) + Send + 'static,
         T: Tuple + 'static,
         <State as Reducer>::Action: Send + 'static,
         <State as Reducer>::Output: Send + From<State> + 'static,
     {
-        Store::runtime(with, dependencies)
+        Store::runtime(with, LocalPool::new, dependencies)
     }
 
     /// Creates a new `Store` with its initial state and a (single) dependency generated by functions.
@@ -57,13 +107,46 @@ impl<State: Reducer> Store<State> {
     /// This is simple wrapper around [`with_dependencies`][`Store::with_dependencies`].
     pub fn with_dependency<F, D, T>(with: F, dependency: D) -> Self
     where
+        State: 'static,
         F: (FnOnce() -> State) + Send + 'static,
         D: (FnOnce() -> T) + Send + 'static,
         T: 'static,
         <State as Reducer>::Action: Send + 'static,
         <State as Reducer>::Output: Send + From<State> + 'static,
     {
-        Store::runtime(with, || (dependency(),))
+        Store::runtime(with, LocalPool::new, || (dependency(),))
+    }
+
+    /// Creates a new `Store` with its initial state, and the [`Executor`] that runs its effects,
+    /// generated by functions.
+    ///
+    /// Both functions are called on the `Store`’s runtime thread, which the executor then runs on;
+    /// so neither the state nor the executor need to be [`Send`]. The other constructors use a
+    /// [`LocalPool`].
+    ///
+    /// ```rust
+    /// # use composable::*;
+    /// # #[derive(Default)]
+    /// # struct State;
+    /// # impl Reducer for State {
+    /// #     type Action = ();
+    /// #     type Output = Self;
+    /// #     fn reduce(&mut self, action: (), send: impl Effects<()>) {}
+    /// # }
+    /// use futures::executor::LocalPool;
+    ///
+    /// let store = Store::with_executor(State::default, LocalPool::new);
+    /// ```
+    pub fn with_executor<F, E, X>(with: F, executor: X) -> Self
+    where
+        State: 'static,
+        F: (FnOnce() -> State) + Send + 'static,
+        E: Executor,
+        X: (FnOnce() -> E) + Send + 'static,
+        <State as Reducer>::Action: Send + 'static,
+        <State as Reducer>::Output: Send + From<State> + 'static,
+    {
+        Store::runtime(with, executor, || ((),))
     }
 
     /// Calls the `Store`’s [`Reducer`][`crate::Reducer`] with `action`.
@@ -71,8 +154,100 @@ impl<State: Reducer> Store<State> {
     /// Takes an [`Into<Action>`] so that both child and parent `Action`s may be sent easily.
     ///
     /// This method is non-blocking: it enqueues the action for the runtime thread to process.
+    /// Unless the `Store` is [`bounded`][`Store::bounded`] with [`Backpressure::Block`], and its
+    /// queue is full.
+    ///
+    /// If the `Store`’s runtime has stopped the action is dropped; use
+    /// [`try_send`][`Store::try_send`] to find out.
     pub fn send(&self, action: impl Into<<State as Reducer>::Action>) {
-        self.sender.send(Ok(action.into()))
+        self.sender.send_bounded(Message::Action(action.into()))
+    }
+
+    /// Attempts to send `action` to the `Store` without blocking.
+    ///
+    /// Returns [`SendError::Full`], containing the `action`, if the `Store` is
+    /// [`bounded`][`Store::bounded`], its queue is full and its [`Backpressure`] policy would
+    /// otherwise have blocked, or dropped the `action`. Or [`SendError::Disconnected`] if the
+    /// `Store`’s runtime has stopped.
+    pub fn try_send(
+        &self,
+        action: impl Into<<State as Reducer>::Action>,
+    ) -> Result<(), SendError<<State as Reducer>::Action>> {
+        self.sender
+            .try_send_bounded(Message::Action(action.into()))
+            .map_err(|err| err.map(Message::into_action))
+    }
+
+    /// Limits the number of actions that may be queued by [`send`][`Store::send`] and
+    /// [`try_send`][`Store::try_send`] to `capacity`, applying `policy` once it is reached.
+    ///
+    /// Actions sent by the `Store`’s own effects are never limited, as an effect waiting on its
+    /// own `Store` would wait forever. Neither are those sent by [`sync`][`Store::sync`] or
+    /// [`send_and_wait`][`Store::send_and_wait`], as their callers already wait on the `Store`.
+    ///
+    /// # Panics
+    /// If `capacity` is zero.
+    pub fn bounded(self, capacity: usize, policy: Backpressure<<State as Reducer>::Action>) -> Self
+    where
+        <State as Reducer>::Action: 'static,
+    {
+        let policy = policy.map(|message| match message {
+            Message::Action(action) => Some(action),
+            _ => None,
+        });
+
+        self.sender.set_bound(capacity, policy);
+        self
+    }
+
+    /// Sets what the `Store` does when its [`Reducer`] panics; see [`PanicPolicy`].
+    pub fn on_panic(self, policy: PanicPolicy<State>) -> Self
+    where
+        State: 'static,
+        <State as Reducer>::Action: 'static,
+    {
+        self.on_runtime(move |runtime| runtime.on_panic(policy));
+        self
+    }
+
+    /// Returns `false` once the `Store`’s runtime has stopped; after a panic, for example.
+    pub fn is_alive(&self) -> bool {
+        !self.sender.is_closed()
+    }
+
+    /// Returns the number of actions sent by [`send`][`Store::send`] or
+    /// [`try_send`][`Store::try_send`] that the `Store` has not yet started to process.
+    ///
+    /// Producers can use this to adapt to a `Store` that is falling behind.
+    pub fn queue_depth(&self) -> usize {
+        self.sender.depth()
+    }
+
+    /// Pauses every timer of the `Store`’s effects; for example, while an app is in the background.
+    ///
+    /// Delays, intervals, debounces and the like do not fire until the timers are
+    /// [resumed](Store::resume_timers). Timers started while paused wait as well.
+    ///
+    /// Like [`send`][`Store::send`], the timers are paused in order with any actions already sent.
+    pub fn pause_timers(&self)
+    where
+        State: 'static,
+        <State as Reducer>::Action: 'static,
+    {
+        self.on_runtime(|_| Dependency::<Reactor>::get().pause());
+    }
+
+    /// Resumes the timers paused by [`pause_timers`][`Store::pause_timers`]; see [`Resume`] for
+    /// the choice of how.
+    ///
+    /// Either way, [`every`][`crate::effects::Scheduler::every`] intervals do not send the ticks
+    /// they missed while paused.
+    pub fn resume_timers(&self, resume: Resume)
+    where
+        State: 'static,
+        <State as Reducer>::Action: 'static,
+    {
+        self.on_runtime(move |_| Dependency::<Reactor>::get().resume(resume));
     }
 
     /// Calls the `Store`’s [`Reducer`][`crate::Reducer`] with `action`. and waits until
@@ -84,29 +259,249 @@ impl<State: Reducer> Store<State> {
     /// However, `sync` *does* wait for any synchronous follow-up actions emitted during this
     /// action’s handling to be drained, since the runtime drains those before it returns to
     /// awaiting the next external action.
-    pub fn sync(&self, action: impl Into<<State as Reducer>::Action>) {
-        self.sender.sync(Ok(action.into()))
+    ///
+    /// # Errors
+    /// Returns [`SendError::Disconnected`] if the `Store`’s runtime has already stopped, and
+    /// [`SendError::Reentrant`] if called on the runtime’s own thread; from within a reducer or
+    /// one of its effects, where it would otherwise wait for itself forever. Use
+    /// [`send_and_wait`][`Store::send_and_wait`] in an effect instead.
+    ///
+    /// If the runtime stops before it reaches `action`, `sync` returns
+    /// [`SendError::Disconnected`] once it has stopped; or [`SendError::Panicked`] if it stops
+    /// because of a panic while processing `action`, unless its [`PanicPolicy`] recovers.
+    pub fn sync(
+        &self,
+        action: impl Into<<State as Reducer>::Action>,
+    ) -> Result<(), SendError<<State as Reducer>::Action>> {
+        self.sender
+            .sync(Message::Action(action.into()))
+            .map_err(|err| err.map(Message::into_action))
+    }
+
+    /// Calls the `Store`’s [`Reducer`][`crate::Reducer`] with `action` and returns a future that
+    /// resolves once the `Reducer` has performed the `action`.
+    ///
+    /// This is the asynchronous equivalent of [`sync`][`Store::sync`], with the same guarantees;
+    /// but it never blocks the calling thread. The returned future does not depend on any
+    /// particular executor.
+    ///
+    /// The `action` is sent immediately, rather than when the returned future is first polled.
+    ///
+    /// # Errors
+    /// The future resolves to [`SendError::Disconnected`] if the `Store`’s runtime has already
+    /// stopped, or stops before it reaches `action`; and to [`SendError::Panicked`] if it stops
+    /// because of a panic while processing `action`.
+    pub fn send_and_wait(
+        &self,
+        action: impl Into<<State as Reducer>::Action>,
+    ) -> impl Future<Output = Result<(), SendError<<State as Reducer>::Action>>> + Send + 'static
+    where
+        <State as Reducer>::Action: Send + 'static,
+    {
+        self.sender
+            .send_and_wait(Message::Action(action.into()))
+            .map(|result| result.map_err(|err| err.map(Message::into_action)))
+    }
+
+    /// Returns a [`Stream`][`futures::Stream`] of values projected from the `Store`’s state.
+    ///
+    /// The current value is emitted first. After that a new value is emitted each time an action
+    /// received by the `Store` (along with any synchronous follow-up actions) has been processed,
+    /// but only if the value has changed since the last one was emitted.
+    ///
+    /// The stream ends when the `Store` shuts down.
+    pub fn subscribe<T, F>(&self, projection: F) -> Subscription<T>
+    where
+        State: 'static,
+        <State as Reducer>::Action: 'static,
+        F: Fn(&State) -> T + Send + 'static,
+        T: Clone + PartialEq + Send + 'static,
+    {
+        let (sender, receiver) = unbounded();
+        let mut previous = None;
+
+        self.on_runtime(move |runtime| {
+            runtime.subscribe(Box::new(move |state| {
+                let value = projection(state);
+                if previous.as_ref() == Some(&value) {
+                    return !sender.is_closed();
+                }
+
+                previous = Some(value.clone());
+                sender.unbounded_send(value).is_ok()
+            }))
+        });
+
+        Subscription::new(receiver)
+    }
+
+    /// Calls `f` with the `Store`’s current state and returns its result.
+    ///
+    /// `f` runs on the `Store`’s runtime thread, so the state does not need to be [`Send`] or
+    /// [`Clone`]. Like [`sync`][`Store::sync`], it runs only after every action already sent
+    /// has been processed, including any synchronous follow-up actions.
+    ///
+    /// # Note
+    /// This is a blocking call. From asynchronous code prefer
+    /// [`with_state_async`][`Store::with_state_async`].
+    ///
+    /// # Errors
+    /// Returns [`SendError::Disconnected`] if the `Store`’s runtime has stopped, or stops before
+    /// it calls `f`; and [`SendError::Reentrant`] if called on the runtime’s own thread, where it
+    /// would otherwise wait for itself forever.
+    pub fn with_state<R, F>(&self, f: F) -> Result<R, SendError<()>>
+    where
+        State: 'static,
+        <State as Reducer>::Action: 'static,
+        F: FnOnce(&State) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        if self.sender.on_receiver_thread() {
+            return Err(SendError::Reentrant(()));
+        }
+
+        // Not a future, like `sync`: this thread may already be running an executor.
+        let (sender, receiver) = std::sync::mpsc::sync_channel(1);
+
+        self.on_runtime(move |runtime| {
+            sender.send(f(runtime.state())).ok();
+        });
+
+        // `f` is dropped, unsent, if the runtime stops before calling it
+        receiver.recv().map_err(|_| SendError::Disconnected(()))
+    }
+
+    /// The asynchronous version of [`with_state`][`Store::with_state`].
+    ///
+    /// `f` is sent to the runtime thread immediately, rather than when the returned future is first polled.
+    ///
+    /// # Errors
+    /// The future resolves to [`SendError::Disconnected`] if the `Store`’s runtime has stopped,
+    /// or stops before it calls `f`.
+    pub fn with_state_async<R, F>(
+        &self,
+        f: F,
+    ) -> impl Future<Output = Result<R, SendError<()>>> + Send + 'static
+    where
+        State: 'static,
+        <State as Reducer>::Action: 'static,
+        F: FnOnce(&State) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (sender, receiver) = oneshot::channel();
+
+        self.on_runtime(move |runtime| {
+            sender.send(f(runtime.state())).ok();
+        });
+
+        receiver.map(|result| result.map_err(|_| SendError::Disconnected(())))
     }
 
-    /// Stops the `Store`’s runtime and returns its current `state` value.  
+    /// Adds `middleware` to the end of the `Store`’s [`Middleware`] chain.
+    ///
+    /// The middleware sees every action processed after those already sent.
+    pub fn add_middleware<M>(&self, middleware: M)
+    where
+        State: 'static,
+        <State as Reducer>::Action: 'static,
+        M: Middleware<State> + Send + 'static,
+    {
+        self.on_runtime(move |runtime| runtime.add_middleware(Box::new(middleware)));
+    }
+
+    /// Starts recording every action the `Store` processes; see [`recording`] for more.
+    ///
+    /// Only actions processed after those already sent are recorded.
+    pub fn record(&self) -> Recorder<<State as Reducer>::Action>
+    where
+        State: 'static,
+        <State as Reducer>::Action: Clone + Send + 'static,
+    {
+        let recorder = Recorder {
+            entries: Arc::new(Mutex::new(Vec::new())),
+        };
+
+        let entries = Arc::downgrade(&recorder.entries);
+        self.on_runtime(move |runtime| {
+            let start = Instant::now();
+
+            runtime.record(Box::new(move |origin, action| {
+                let Some(entries) = entries.upgrade() else {
+                    return false; // the `Recorder` has been dropped
+                };
+
+                let mut entries = entries.lock().unwrap_or_else(|err| err.into_inner());
+                entries.push(Entry {
+                    at: start.elapsed(),
+                    origin,
+                    action: action.clone(),
+                });
+
+                true
+            }))
+        });
+
+        recorder
+    }
+
+    /// Sends the [external][`Origin::External`] actions in `recording` to the `Store`, spaced out
+    /// in time as they were when they were recorded.
+    ///
+    /// Recorded [effect][`Origin::Effect`] actions are not sent, as the `Store`’s own effects are
+    /// expected to send them again.
+    ///
+    /// # Note
+    /// This is a blocking call; it sleeps between actions and returns once the last action has
+    /// been sent.
+    pub fn replay(&self, recording: &Recording<<State as Reducer>::Action>)
+    where
+        <State as Reducer>::Action: Clone,
+    {
+        let start = Instant::now();
+
+        for entry in recording {
+            if entry.origin == Origin::External {
+                sleep(entry.at.saturating_sub(start.elapsed()));
+                self.send(entry.action.clone());
+            }
+        }
+    }
+
+    /// Runs `f` on the runtime thread, in order with any actions already sent.
+    fn on_runtime<F>(&self, f: F)
+    where
+        State: 'static,
+        <State as Reducer>::Action: 'static,
+        F: FnOnce(&mut Runtime<State>) + Send + 'static,
+    {
+        self.sender.send(Message::Runtime(Box::new(move |runtime| {
+            f(runtime.downcast_mut().expect("Runtime<State>"))
+        })))
+    }
+
+    /// Stops the `Store`’s runtime and returns its current `state` value.
+    ///
+    /// Returns a [`StoreError`] if the runtime has already stopped because of a panic, rather than
+    /// panicking itself.
     ///
     /// # Note
     /// Care should be exercised when using this method in applications that utilize
     /// asynchronous [`Effects`][`crate::effects::Effects`]. `into_inner` makes a best effort to
     /// allow pending tasks to run before shutdown, but completion is not guaranteed.
-    pub fn into_inner(self) -> <State as Reducer>::Output {
-        self.sender.send(Err(std::thread::current()));
-        std::thread::park(); // waiting for any async tasks to finish up
+    pub fn into_inner(self) -> Result<<State as Reducer>::Output, StoreError> {
+        let (done, finished) = oneshot::channel();
+        self.sender.send(Message::Shutdown(done));
+        block_on(finished).ok(); // waiting for any async tasks to finish up (or for the runtime to stop)
 
         drop(self.sender); // ends the runtime’s (outer) while-let
         std::thread::yield_now(); // give it time to shut down
-        self.handle.join().unwrap()
+        self.handle.join().map_err(StoreError::panicked)
     }
 }
 
 impl<State: Reducer> Default for Store<State>
 where
-    State: Default,
+    State: Default + 'static,
     <State as Reducer>::Action: Send + 'static,
     <State as Reducer>::Output: Send + From<State> + 'static,
 {
@@ -0,0 +1,419 @@
+//! Action recording and deterministic replay.
+//!
+//! A [`Recorder`] captures every action a [`Store`] processes, along with its [`Origin`] and the
+//! time at which it was processed. The resulting [`Recording`] can be written to (and read from)
+//! a compact binary format so that, for example, a bug report from the field becomes a test case.
+//!
+//! - [`Store::replay`] sends the recorded external actions to a fresh `Store`, with the same
+//!   spacing in (real) time.
+//! - [`TestStore::replay`] does the same using [`TestClock::advance`], and also checks that every
+//!   recorded effect action is emitted again, in the same order.
+//!
+//! # File format
+//!
+//! A recording begins with the four byte header `TCA\x01`, followed by one frame per action:
+//!
+//! | field    | encoding                                           |
+//! |----------|----------------------------------------------------|
+//! | `delta`  | LEB128; microseconds since the previous action     |
+//! | `origin` | one byte; `0` for external, `1` for effect actions |
+//! | `length` | LEB128; byte length of the encoded action          |
+//! | `action` | `length` bytes, as produced by the `encode` closure |
+//!
+//! The encoding of the actions themselves is left to the caller, so any serialization library
+//! (or a hand-written format) can be used.
+//!
+//! [`Store`]: crate::Store
+//! [`Store::replay`]: crate::Store::replay
+//! [`TestStore::replay`]: crate::TestStore::replay
+//! [`TestClock::advance`]: crate::TestClock::advance
+
+use std::error::Error;
+use std::io::{self, ErrorKind, Read, Write};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const HEADER: &[u8; 4] = b"TCA\x01";
+
+/// Where an action processed by a `Store` came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Origin {
+    /// Sent to the `Store` from outside; by [`send`][`crate::Store::send`], for example.
+    External,
+    /// Sent by one of the `Store`’s own [`Effects`][`crate::effects::Effects`].
+    Effect,
+}
+
+/// A single recorded action.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry<Action> {
+    /// The time since the recording started.
+    pub at: Duration,
+    pub origin: Origin,
+    pub action: Action,
+}
+
+/// A sequence of recorded actions, in the order they were processed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Recording<Action> {
+    entries: Vec<Entry<Action>>,
+}
+
+impl<Action> Default for Recording<Action> {
+    fn default() -> Self {
+        Self {
+            entries: Default::default(),
+        }
+    }
+}
+
+impl<Action> Recording<Action> {
+    /// Creates a recording from its entries.
+    pub fn new(entries: Vec<Entry<Action>>) -> Self {
+        Self { entries }
+    }
+
+    /// Returns the recorded entries.
+    pub fn entries(&self) -> &[Entry<Action>] {
+        &self.entries
+    }
+
+    /// Returns an iterator over the recorded entries.
+    pub fn iter(&self) -> std::slice::Iter<'_, Entry<Action>> {
+        self.entries.iter()
+    }
+
+    /// Writes the recording to `writer`, using `encode` to convert each action into bytes.
+    ///
+    /// Times are written in whole microseconds; anything finer is truncated.
+    ///
+    /// # Errors
+    /// Returns an error of kind [`InvalidData`][`ErrorKind::InvalidData`] if an entry’s time does
+    /// not fit in 64 bits of microseconds; along with any error from `writer` itself.
+    pub fn write_to<W, E>(&self, mut writer: W, mut encode: E) -> io::Result<()>
+    where
+        W: Write,
+        E: FnMut(&Action) -> Vec<u8>,
+    {
+        writer.write_all(HEADER)?;
+
+        let mut previous = 0;
+        for entry in &self.entries {
+            // truncated before taking the difference, so that the rounding does not accumulate
+            let micros = u64::try_from(entry.at.as_micros())
+                .map_err(|_| io::Error::new(ErrorKind::InvalidData, "time overflow"))?;
+            let delta = micros.saturating_sub(previous);
+            previous = previous.max(micros);
+
+            let bytes = encode(&entry.action);
+            write_varint(&mut writer, delta)?;
+            writer.write_all(&[match entry.origin {
+                Origin::External => 0,
+                Origin::Effect => 1,
+            }])?;
+            write_varint(&mut writer, bytes.len() as u64)?;
+            writer.write_all(&bytes)?;
+        }
+
+        writer.flush()
+    }
+
+    /// Reads a recording previously written by [`write_to`][`Recording::write_to`], using
+    /// `decode` to convert each action back from its bytes.
+    ///
+    /// # Errors
+    /// Returns an error of kind [`UnexpectedEof`][`ErrorKind::UnexpectedEof`] if the recording is
+    /// truncated, and of kind [`InvalidData`][`ErrorKind::InvalidData`] if it is corrupt or
+    /// `decode` fails; along with any error from `reader` itself.
+    pub fn read_from<R, D, E>(mut reader: R, mut decode: D) -> io::Result<Self>
+    where
+        R: Read,
+        D: FnMut(&[u8]) -> Result<Action, E>,
+        E: Into<Box<dyn Error + Send + Sync>>,
+    {
+        let mut header = [0; 4];
+        reader.read_exact(&mut header)?;
+        if &header != HEADER {
+            return Err(io::Error::new(ErrorKind::InvalidData, "not a recording"));
+        }
+
+        let mut entries = Vec::new();
+        let mut at = Duration::ZERO;
+        let mut bytes = Vec::new();
+
+        while let Some(delta) = read_varint(&mut reader)? {
+            let mut origin = [0];
+            reader.read_exact(&mut origin)?;
+            let origin = match origin[0] {
+                0 => Origin::External,
+                1 => Origin::Effect,
+                _ => return Err(io::Error::new(ErrorKind::InvalidData, "unknown origin")),
+            };
+
+            let length = read_varint(&mut reader)?
+                .ok_or_else(|| io::Error::from(ErrorKind::UnexpectedEof))?;
+
+            // read as it arrives, rather than trusting the length enough to allocate it up front
+            bytes.clear();
+            reader.by_ref().take(length).read_to_end(&mut bytes)?;
+            if bytes.len() as u64 != length {
+                return Err(ErrorKind::UnexpectedEof.into());
+            }
+
+            at = at
+                .checked_add(Duration::from_micros(delta))
+                .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "time overflow"))?;
+            let action =
+                decode(&bytes).map_err(|err| io::Error::new(ErrorKind::InvalidData, err))?;
+
+            entries.push(Entry { at, origin, action });
+        }
+
+        Ok(Self { entries })
+    }
+}
+
+impl<'a, Action> IntoIterator for &'a Recording<Action> {
+    type Item = &'a Entry<Action>;
+    type IntoIter = std::slice::Iter<'a, Entry<Action>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// A handle to an in-progress recording.
+///
+/// This `struct` is created by the [`record`] method on [`Store`]. See its documentation for more.
+///
+/// Dropping the `Recorder` stops the recording.
+///
+/// [`record`]: crate::Store::record
+/// [`Store`]: crate::Store
+pub struct Recorder<Action> {
+    pub(crate) entries: Arc<Mutex<Vec<Entry<Action>>>>,
+}
+
+impl<Action> Recorder<Action> {
+    /// Stops recording and returns everything recorded so far.
+    ///
+    /// Actions that are still queued, or that have not been sent yet, are not included.
+    pub fn finish(self) -> Recording<Action> {
+        let mut entries = self.entries.lock().unwrap_or_else(|err| err.into_inner());
+        Recording::new(std::mem::take(&mut *entries))
+    }
+}
+
+fn write_varint(writer: &mut impl Write, mut value: u64) -> io::Result<()> {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+
+        if value == 0 {
+            return writer.write_all(&[byte]);
+        }
+
+        writer.write_all(&[byte | 0x80])?;
+    }
+}
+
+/// Returns `None` on a clean end-of-file; before any of the varint has been read.
+fn read_varint(reader: &mut impl Read) -> io::Result<Option<u64>> {
+    let mut value = 0;
+
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0];
+        if reader.read(&mut byte)? == 0 {
+            return match shift {
+                0 => Ok(None),
+                _ => Err(ErrorKind::UnexpectedEof.into()),
+            };
+        }
+
+        value |= u64::from(byte[0] & 0x7f) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(Some(value));
+        }
+    }
+
+    Err(io::Error::new(ErrorKind::InvalidData, "varint overflow"))
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::ready;
+
+    #[cfg(not(miri))]
+    use ntest_timeout::timeout;
+
+    use crate::{Effects, Reducer, Store, TestStore};
+
+    use super::*;
+
+    #[derive(Clone, Debug, Default, PartialEq)]
+    struct State {
+        log: Vec<u8>,
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Action {
+        Start,
+        Bump,
+        Done,
+    }
+
+    impl Reducer for State {
+        type Action = Action;
+        type Output = Self;
+
+        fn reduce(&mut self, action: Action, send: impl Effects<Action>) {
+            match action {
+                Action::Start => {
+                    self.log.push(0);
+                    send.action(Action::Bump);
+                    send.future(ready(Some(Action::Done)));
+                }
+                Action::Bump => self.log.push(1),
+                Action::Done => self.log.push(2),
+            }
+        }
+    }
+
+    fn encode(action: &Action) -> Vec<u8> {
+        vec![match action {
+            Action::Start => 0,
+            Action::Bump => 1,
+            Action::Done => 2,
+        }]
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Action, &'static str> {
+        match bytes {
+            [0] => Ok(Action::Start),
+            [1] => Ok(Action::Bump),
+            [2] => Ok(Action::Done),
+            _ => Err("unknown action"),
+        }
+    }
+
+    #[test]
+    fn test_file_format_round_trip() {
+        let recording = Recording::new(vec![
+            Entry {
+                at: Duration::from_micros(5),
+                origin: Origin::External,
+                action: Action::Start,
+            },
+            Entry {
+                at: Duration::from_secs(300),
+                origin: Origin::Effect,
+                action: Action::Done,
+            },
+        ]);
+
+        let mut bytes = Vec::new();
+        recording.write_to(&mut bytes, encode).unwrap();
+        assert_eq!(bytes.len(), 4 + (1 + 1 + 1 + 1) + (5 + 1 + 1 + 1));
+
+        let read = Recording::read_from(bytes.as_slice(), decode).unwrap();
+        assert_eq!(read, recording);
+
+        let err = Recording::read_from(&bytes[..bytes.len() - 1], decode).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_sub_microsecond_times_do_not_drift() {
+        let entry = |nanos| Entry {
+            at: Duration::from_nanos(nanos),
+            origin: Origin::External,
+            action: Action::Start,
+        };
+
+        let recording = Recording::new(vec![entry(1_500), entry(2_999), entry(4_500)]);
+
+        let mut bytes = Vec::new();
+        recording.write_to(&mut bytes, encode).unwrap();
+        let read = Recording::read_from(bytes.as_slice(), decode).unwrap();
+
+        let times = read.iter().map(|entry| entry.at);
+        assert_eq!(
+            times.collect::<Vec<_>>(),
+            [1, 2, 4].map(Duration::from_micros)
+        );
+    }
+
+    #[test]
+    fn test_truncated_and_oversized_actions_are_errors() {
+        let mut bytes = HEADER.to_vec();
+        write_varint(&mut bytes, 0).unwrap(); // delta
+        bytes.push(0); // origin
+
+        let mut truncated = bytes.clone();
+        write_varint(&mut truncated, 2).unwrap();
+        truncated.push(0); // one of the two bytes
+
+        let err = Recording::read_from(truncated.as_slice(), decode).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+
+        let mut oversized = bytes;
+        write_varint(&mut oversized, u64::MAX).unwrap(); // never allocated
+        oversized.push(0);
+
+        let err = Recording::read_from(oversized.as_slice(), decode).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    #[cfg(not(miri))]
+    fn test_time_overflow_is_an_error() {
+        let mut bytes = HEADER.to_vec();
+
+        // enough of the longest delta to overflow a `Duration`
+        let entries = Duration::MAX.as_micros() / u128::from(u64::MAX) + 1;
+        for _ in 0..entries {
+            write_varint(&mut bytes, u64::MAX).unwrap();
+            bytes.push(0);
+            write_varint(&mut bytes, 1).unwrap();
+            bytes.push(0); // `Action::Start`
+        }
+
+        let err = Recording::read_from(bytes.as_slice(), decode).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        let recording = Recording::new(vec![Entry {
+            at: Duration::MAX,
+            origin: Origin::External,
+            action: Action::Start,
+        }]);
+
+        let err = recording.write_to(Vec::new(), encode).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    #[cfg(not(miri))]
+    #[timeout(10000)]
+    fn test_recording_replays_into_test_store() {
+        let store = Store::with_initial(State::default());
+        let recorder = store.record();
+
+        store.send(Action::Start);
+        let live = store.into_inner().unwrap();
+        let recording = recorder.finish();
+
+        let origins = recording.iter().map(|entry| entry.origin);
+        assert_eq!(
+            origins.collect::<Vec<_>>(),
+            [Origin::External, Origin::Effect, Origin::Effect]
+        );
+
+        let mut bytes = Vec::new();
+        recording.write_to(&mut bytes, encode).unwrap();
+        let recording = Recording::read_from(bytes.as_slice(), decode).unwrap();
+
+        let mut store = TestStore::<State>::default();
+        store.replay(&recording);
+        assert_eq!(store.into_inner(), live);
+    }
+}
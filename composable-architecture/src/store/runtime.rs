@@ -8,31 +8,43 @@
 //! - While handling an external action, any synchronous effect actions emitted are queued and
 //!   drained *before* processing the next external action. This makes internal effect chains
 //!   uninterruptible by subsequent external sends.
-//! - Shutdown uses a small handshake: `Store::into_inner` sends a sentinel containing the calling
-//!   thread handle; the runtime schedules an `unpark` on its local executor to allow pending tasks
+//! - Effect tasks take turns with the runtime: each sends a bounded number of actions before
+//!   yielding, and a [`Background`](crate::Priority::Background) task sends none while external
+//!   actions are queued.
+//! - Subscribers are notified once an external action and its synchronous follow-ups have been
+//!   drained; never part way through such a chain.
+//! - Shutdown uses a small handshake: `Store::into_inner` sends a sentinel containing a one-shot
+//!   sender; the runtime schedules its completion on its local executor to allow pending tasks
 //!   to make progress before exit.
+//! - A panic in the reducer (or its middleware) is handled according to the store’s
+//!   [`PanicPolicy`]. Any other panic stops the runtime.
 
 use std::cell::RefCell;
 use std::collections::VecDeque;
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
 use std::rc::Rc;
-use std::thread::{Builder, Thread};
+use std::thread::Builder;
 
-use futures::executor::LocalPool;
 use futures::task::LocalSpawnExt;
 use futures::{pin_mut, StreamExt};
 
-use crate::dependencies::{with_dependencies, with_dependency, Tuple};
-use crate::effects::Executor;
+use crate::dependencies::{with_dependencies, Tuple};
+use crate::effects::{cancellation::Cancellations, Executor, Spawner};
 use crate::reducer::Reducer;
 use crate::store::channel::{channel, WeakSender};
-use crate::store::Store;
+use crate::store::middleware::{Chain, Middleware};
+use crate::store::recording::Origin;
+use crate::store::{Message, Store};
 
 impl<State: Reducer> Store<State> {
     /// Constructs a store running on a dedicated thread with an injected dependency tuple.
     ///
     /// This is the shared implementation behind `with_initial`, `with_dependency`, and `with_dependencies`.
-    pub(crate) fn runtime<F, D, T>(with: F, dependencies: D) -> Self
+    pub(crate) fn runtime<E, X, F, D, T>(with: F, executor: X, dependencies: D) -> Self
     where
+        State: 'static,
+        E: Executor,
+        X: (FnOnce() -> E) + Send + 'static,
         F: (FnOnce() -> State) + Send + 'static,
         D: (FnOnce() -> T) + Send + 'static,
         T: Tuple + 'static,
@@ -40,55 +52,49 @@ impl<State: Reducer> Store<State> {
         <State as Reducer>::Output: Send + From<State> + 'static,
     {
         let (sender, receiver) = channel();
-        let actions: WeakSender<Result<<State as Reducer>::Action, Thread>> = sender.downgrade();
+        let actions: WeakSender<Message<<State as Reducer>::Action>> = sender.downgrade();
 
         let handle = Builder::new()
             .name(std::any::type_name::<State>().into())
             .spawn(move || {
-                let mut unthreaded = LocalPool::new();
+                let mut unthreaded = executor();
                 let spawner = unthreaded.spawner();
 
-                let mut state = with();
+                let mut runtime = Runtime::new(with());
                 let receiver = receiver.upgrade().unwrap();
-                let effects = Rc::new(RefCell::new(VecDeque::new()));
 
-                let executor = Executor::new(spawner.clone(), actions);
+                let effects = Spawner::new(spawner.clone(), actions);
                 let dependencies = dependencies();
 
-                with_dependency(executor, || {
+                with_dependencies((effects, Cancellations::default()), || {
                     with_dependencies(dependencies, || {
-                        unthreaded.run_until(async {
+                        unthreaded.block_on(async {
                             pin_mut!(receiver);
-                            while let Some(result) = receiver.next().await {
-                                match result {
-                                    Ok(action) => {
-                                        state.reduce(action, Rc::downgrade(&effects));
-
-                                        // Wrap the `borrow_mut` in a closure to ensure the borrow is dropped immediately,
-                                        // `borrow_mut` is dropped immediately so that the action is
-                                        // free to push further actions to `effects`
-                                        let next = || effects.borrow_mut().pop_front();
-
-                                        while let Some(action) = next() {
-                                            state.reduce(action, Rc::downgrade(&effects));
-                                        }
+                            while let Some(message) = receiver.next().await {
+                                match message {
+                                    Message::Action(action) => {
+                                        runtime.reduce(action, Origin::External)
                                     }
-                                    Err(parked) => {
+                                    Message::Effect(action) => {
+                                        runtime.reduce(action, Origin::Effect)
+                                    }
+                                    Message::Runtime(f) => f(&mut runtime),
+                                    Message::Shutdown(done) => {
                                         spawner
-                                            // `unpark` a thread that is waiting for the store to shut down;
+                                            // notify a thread that is waiting for the store to shut down;
                                             //  we use a future so that it happens after other (waiting) futures
                                             //
                                             //  See: `Store::into_inner` for the other side of this
                                             .spawn_local(async move {
-                                                parked.unpark();
+                                                done.send(()).ok();
                                             })
-                                            .expect("unpark");
+                                            .expect("shutdown");
                                     }
                                 }
                             }
                         });
 
-                        state.into()
+                        runtime.into_inner().into()
                     })
                 })
             })
@@ -98,6 +104,168 @@ impl<State: Reducer> Store<State> {
     }
 }
 
+/// What a [`Store`] does when its [`Reducer`] panics.
+///
+/// The policy covers the whole of an action’s handling: the action itself and any synchronous
+/// follow-up actions, along with any [`Middleware`] they pass through. Panics elsewhere, such as in
+/// an asynchronous effect, always stop the `Store`.
+///
+/// See [`Store::on_panic`].
+pub struct PanicPolicy<State> {
+    recovery: Recovery<State>,
+}
+
+enum Recovery<State> {
+    Stop,
+    Skip(fn(&State) -> State),
+    Restart(Box<dyn FnMut() -> State + Send>),
+}
+
+impl<State> PanicPolicy<State> {
+    /// Stops the `Store`’s runtime. This is the default.
+    ///
+    /// Afterwards [`Store::is_alive`] returns `false`, [`Store::try_send`] returns an error and
+    /// [`Store::into_inner`] returns the panic.
+    pub fn stop() -> Self {
+        Self {
+            recovery: Recovery::Stop,
+        }
+    }
+
+    /// Skips the action and continues with the state as it was before the action was sent.
+    ///
+    /// The state is cloned before every action, which may be expensive for large states.
+    pub fn skip() -> Self
+    where
+        State: Clone,
+    {
+        Self {
+            recovery: Recovery::Skip(State::clone),
+        }
+    }
+
+    /// Continues with a new state created by `factory`.
+    pub fn restart<F>(factory: F) -> Self
+    where
+        F: FnMut() -> State + Send + 'static,
+    {
+        Self {
+            recovery: Recovery::Restart(Box::new(factory)),
+        }
+    }
+}
+
+impl<State> Default for PanicPolicy<State> {
+    fn default() -> Self {
+        Self::stop()
+    }
+}
+
+/// The state owned by a `Store`’s runtime thread.
+pub(crate) struct Runtime<State: Reducer> {
+    state: State,
+    effects: Rc<RefCell<VecDeque<<State as Reducer>::Action>>>,
+    middleware: Chain<State>,
+    on_panic: Recovery<State>,
+    recorders: Vec<Recorder<<State as Reducer>::Action>>,
+    subscribers: Vec<Subscriber<State>>,
+}
+
+/// Returns `false` once it is no longer recording and should be dropped.
+type Recorder<Action> = Box<dyn FnMut(Origin, &Action) -> bool>;
+
+/// Returns `false` once it is no longer interested in the state and should be dropped.
+type Subscriber<State> = Box<dyn FnMut(&State) -> bool>;
+
+impl<State: Reducer> Runtime<State>
+where
+    <State as Reducer>::Action: 'static,
+{
+    pub(crate) fn new(state: State) -> Self {
+        Self {
+            state,
+            effects: Default::default(),
+            middleware: Default::default(),
+            on_panic: Recovery::Stop,
+            recorders: Default::default(),
+            subscribers: Default::default(),
+        }
+    }
+
+    /// Reduces `action`, then drains any synchronous follow-up actions before notifying subscribers.
+    ///
+    /// If any of them panic, the `PanicPolicy` decides what happens to the state.
+    pub(crate) fn reduce(&mut self, action: <State as Reducer>::Action, origin: Origin) {
+        let snapshot = match &self.on_panic {
+            Recovery::Skip(clone) => Some(clone(&self.state)),
+            _ => None,
+        };
+
+        let reduced = catch_unwind(AssertUnwindSafe(|| self.reduce_all(action, origin)));
+
+        if let Err(panic) = reduced {
+            self.effects.borrow_mut().clear(); // follow-ups of the failed action
+
+            match (&mut self.on_panic, snapshot) {
+                (Recovery::Skip(_), Some(snapshot)) => self.state = snapshot,
+                (Recovery::Restart(factory), _) => self.state = factory(),
+                _ => resume_unwind(panic),
+            }
+        }
+
+        let state = &self.state;
+        self.subscribers.retain_mut(|subscriber| subscriber(state));
+    }
+
+    fn reduce_all(&mut self, action: <State as Reducer>::Action, origin: Origin) {
+        self.recorders
+            .retain_mut(|recorder| recorder(origin, &action));
+
+        let effects = Rc::downgrade(&self.effects);
+        self.middleware.reduce(&mut self.state, action, effects);
+
+        // Wrap the `borrow_mut` in a closure to ensure the borrow is dropped immediately,
+        // `borrow_mut` is dropped immediately so that the action is
+        // free to push further actions to `effects`
+        let next = || self.effects.borrow_mut().pop_front();
+
+        while let Some(action) = next() {
+            self.recorders
+                .retain_mut(|recorder| recorder(Origin::Effect, &action));
+
+            let effects = Rc::downgrade(&self.effects);
+            self.middleware.reduce(&mut self.state, action, effects);
+        }
+    }
+
+    /// Adds a subscriber, immediately calling it with the current state.
+    pub(crate) fn subscribe(&mut self, mut subscriber: Subscriber<State>) {
+        if subscriber(&self.state) {
+            self.subscribers.push(subscriber);
+        }
+    }
+
+    pub(crate) fn on_panic(&mut self, policy: PanicPolicy<State>) {
+        self.on_panic = policy.recovery;
+    }
+
+    pub(crate) fn add_middleware(&mut self, middleware: Box<dyn Middleware<State>>) {
+        self.middleware.push(middleware);
+    }
+
+    pub(crate) fn record(&mut self, recorder: Recorder<<State as Reducer>::Action>) {
+        self.recorders.push(recorder);
+    }
+
+    pub(crate) fn state(&self) -> &State {
+        &self.state
+    }
+
+    pub(crate) fn into_inner(self) -> State {
+        self.state
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
     use std::sync::{Arc, Mutex};
@@ -188,6 +356,561 @@ pub mod tests {
         assert_eq!(*values, vec!['1', 'A', 'B', 'C', 'D', '2', '3']);
     }
 
+    #[test]
+    #[cfg(not(miri))]
+    #[timeout(10000)]
+    /// A stream that is always ready takes turns with the `Store`, rather than taking it over.
+    fn test_chatty_effects_do_not_starve_external_actions() {
+        use futures::stream::repeat;
+
+        use crate::Priority;
+
+        #[derive(Default)]
+        struct State {
+            ticks: usize,
+            pings: usize,
+        }
+
+        #[derive(Clone, Debug)]
+        enum Action {
+            Start(Priority),
+            Stop,
+            Tick,
+            Ping,
+        }
+
+        impl Reducer for State {
+            type Action = Action;
+            type Output = usize;
+
+            fn reduce(&mut self, action: Action, send: impl Effects<Action>) {
+                match action {
+                    Action::Start(priority) => send
+                        .task(repeat(Action::Tick))
+                        .priority(priority)
+                        .cancellable("chatty"),
+                    Action::Stop => send.cancel("chatty"),
+                    Action::Tick => self.ticks += 1,
+                    Action::Ping => self.pings += 1,
+                }
+            }
+        }
+
+        impl From<State> for usize {
+            fn from(value: State) -> Self {
+                value.pings
+            }
+        }
+
+        for priority in [Priority::Normal, Priority::Background] {
+            let store = Store::<State>::default();
+
+            store.sync(Action::Start(priority)).unwrap();
+            for _ in 0..10 {
+                // would never return if the stream kept the thread
+                store.sync(Action::Ping).unwrap();
+            }
+            store.sync(Action::Stop).unwrap();
+
+            assert_eq!(store.into_inner().unwrap(), 10);
+        }
+    }
+
+    #[test]
+    #[cfg(not(miri))]
+    #[timeout(10000)]
+    /// A `Background` task waits while an action is queued by `send_and_wait`, or `sync`; not only
+    /// by `send`.
+    fn test_background_effects_wait_for_external_actions() {
+        use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+        use std::sync::{OnceLock, Weak};
+
+        use futures::executor::block_on;
+        use futures::stream::repeat_with;
+
+        use crate::Priority;
+
+        #[derive(Default)]
+        struct State {
+            store: Arc<OnceLock<Weak<Store<State>>>>,
+            queued: Arc<AtomicBool>,
+            pinged: Arc<AtomicBool>,
+            /// Items pulled by the `Background` task while `Ping` was waiting to be reduced.
+            early: Arc<AtomicUsize>,
+        }
+
+        #[derive(Clone, Debug)]
+        enum Action {
+            Start,
+            Stop,
+            Tick,
+            Ping,
+        }
+
+        impl Reducer for State {
+            type Action = Action;
+            type Output = usize;
+
+            fn reduce(&mut self, action: Action, send: impl Effects<Action>) {
+                match action {
+                    Action::Start => {
+                        // spawned first, so it runs first; queueing `Ping` from outside the reducer
+                        let (store, queued) = (self.store.clone(), self.queued.clone());
+                        send.future(async move {
+                            let store = store.get().and_then(Weak::upgrade).unwrap();
+                            drop(store.send_and_wait(Action::Ping)); // queued, but not awaited
+                            queued.store(true, Ordering::SeqCst);
+                            None
+                        });
+
+                        let (queued, pinged) = (self.queued.clone(), self.pinged.clone());
+                        let early = self.early.clone();
+                        send.task(repeat_with(move || {
+                            if queued.load(Ordering::SeqCst) && !pinged.load(Ordering::SeqCst) {
+                                early.fetch_add(1, Ordering::SeqCst);
+                            }
+                            Action::Tick
+                        }))
+                        .priority(Priority::Background)
+                        .cancellable("background");
+                    }
+                    Action::Stop => send.cancel("background"),
+                    Action::Tick => {}
+                    Action::Ping => self.pinged.store(true, Ordering::SeqCst),
+                }
+            }
+        }
+
+        impl From<State> for usize {
+            fn from(value: State) -> Self {
+                value.early.load(Ordering::SeqCst)
+            }
+        }
+
+        let handle = Arc::new(OnceLock::new());
+        let pinged = Arc::new(AtomicBool::new(false));
+        let store = Arc::new(Store::with_initial(State {
+            store: handle.clone(),
+            pinged: pinged.clone(),
+            ..Default::default()
+        }));
+        handle.set(Arc::downgrade(&store)).unwrap();
+
+        store.sync(Action::Start).unwrap();
+        while !pinged.load(Ordering::SeqCst) {
+            block_on(store.send_and_wait(Action::Tick)).unwrap();
+        }
+        store.sync(Action::Stop).unwrap();
+
+        let store = Arc::into_inner(store).unwrap();
+        assert_eq!(store.into_inner().unwrap(), 0);
+    }
+
+    #[test]
+    #[cfg(not(miri))]
+    #[timeout(10000)]
+    /// A reducer that syncs with its own `Store` gets an error, rather than waiting for itself.
+    fn test_reentrant_sync_is_an_error() {
+        use std::sync::{OnceLock, Weak};
+
+        use crate::SendError;
+
+        type Results = (
+            Option<Result<(), SendError<Action>>>,
+            Option<Result<bool, SendError<()>>>,
+        );
+
+        #[derive(Default)]
+        struct State {
+            store: Arc<OnceLock<Weak<Store<State>>>>,
+            results: Results,
+        }
+
+        #[derive(Debug, PartialEq)]
+        enum Action {
+            Reenter,
+            Ping,
+        }
+
+        impl Reducer for State {
+            type Action = Action;
+            type Output = Results;
+
+            fn reduce(&mut self, action: Action, _send: impl Effects<Action>) {
+                if let Action::Reenter = action {
+                    let store = self.store.get().and_then(Weak::upgrade).unwrap();
+                    self.results.0 = Some(store.sync(Action::Ping));
+                    self.results.1 = Some(store.with_state(|_| true));
+                }
+            }
+        }
+
+        impl From<State> for Results {
+            fn from(value: State) -> Self {
+                value.results
+            }
+        }
+
+        let handle = Arc::new(OnceLock::new());
+        let store = Arc::new(Store::with_initial(State {
+            store: handle.clone(),
+            results: Default::default(),
+        }));
+        handle.set(Arc::downgrade(&store)).unwrap();
+
+        store.sync(Action::Reenter).unwrap();
+
+        let store = Arc::into_inner(store).unwrap();
+        let (synced, queried) = store.into_inner().unwrap();
+        assert_eq!(synced, Some(Err(SendError::Reentrant(Action::Ping))));
+        assert_eq!(queried, Some(Err(SendError::Reentrant(()))));
+    }
+
+    #[test]
+    #[cfg(not(miri))]
+    #[timeout(10000)]
+    /// A reducer can sync with another `Store`; even though its own runtime is running an executor.
+    fn test_sync_with_another_store_from_a_reducer() {
+        #[derive(Default)]
+        struct Counter {
+            count: usize,
+        }
+
+        impl Reducer for Counter {
+            type Action = ();
+            type Output = usize;
+
+            fn reduce(&mut self, _action: (), _send: impl Effects<()>) {
+                self.count += 1;
+            }
+        }
+
+        impl From<Counter> for usize {
+            fn from(value: Counter) -> Self {
+                value.count
+            }
+        }
+
+        struct Forwarder {
+            counter: Arc<Store<Counter>>,
+        }
+
+        impl Reducer for Forwarder {
+            type Action = ();
+            type Output = Self;
+
+            fn reduce(&mut self, action: (), _send: impl Effects<()>) {
+                self.counter.sync(action).unwrap();
+            }
+        }
+
+        let counter = Arc::new(Store::with_initial(Counter::default()));
+        let forwarder = Store::with_initial(Forwarder {
+            counter: counter.clone(),
+        });
+
+        forwarder.sync(()).unwrap();
+        forwarder.sync(()).unwrap();
+        assert!(forwarder.into_inner().is_ok());
+
+        let counter = Arc::into_inner(counter).unwrap();
+        assert_eq!(counter.into_inner(), Ok(2));
+    }
+
+    #[test]
+    #[cfg(not(miri))]
+    #[timeout(10000)]
+    /// Subscribers see the initial value, then one value per drained action chain, but only
+    /// when the projected value actually changes.
+    fn test_subscribe_emits_changed_values() {
+        use futures::executor::block_on;
+
+        let store = Store::with_initial(State::default());
+        let lengths = store.subscribe(|state| state.characters.lock().unwrap().len());
+
+        use Action::*;
+        store.send(External('1')); // 1 + 4 internal actions → a single emission
+        store.send(Internal('2'));
+        store.sync(Internal('3')).unwrap();
+
+        let characters = store.subscribe(|state| state.characters.lock().unwrap().clone());
+        store.into_inner().unwrap();
+
+        assert_eq!(block_on(lengths.collect::<Vec<_>>()), vec![0, 5, 6, 7]);
+        assert_eq!(
+            block_on(characters.collect::<Vec<_>>()),
+            vec![vec!['1', 'A', 'B', 'C', 'D', '2', '3']]
+        );
+    }
+
+    #[test]
+    #[cfg(not(miri))]
+    #[timeout(10000)]
+    /// State queries run after the actions sent before them, including their follow-ups.
+    fn test_with_state_is_ordered_with_actions() {
+        use std::cell::Cell;
+
+        #[derive(Default)]
+        struct State(Rc<Cell<usize>>); // not `Send`
+
+        #[derive(Debug)]
+        enum Action {
+            Increment(usize),
+        }
+
+        impl Reducer for State {
+            type Action = Action;
+            type Output = usize;
+
+            fn reduce(&mut self, action: Action, send: impl Effects<Action>) {
+                let Action::Increment(n) = action;
+                self.0.set(self.0.get() + 1);
+
+                if n > 0 {
+                    send.action(Action::Increment(n - 1));
+                }
+            }
+        }
+
+        impl From<State> for usize {
+            fn from(value: State) -> Self {
+                value.0.get()
+            }
+        }
+
+        let store = Store::with_dependencies(State::default, || ((),));
+
+        store.send(Action::Increment(2));
+        assert_eq!(store.with_state(|state| state.0.get()), Ok(3));
+
+        store.send(Action::Increment(0));
+        let pending = store.with_state_async(|state| state.0.get());
+        store.send(Action::Increment(0));
+
+        assert_eq!(futures::executor::block_on(pending), Ok(4));
+        assert_eq!(store.into_inner().unwrap(), 5);
+    }
+
+    #[test]
+    #[cfg(not(miri))]
+    #[timeout(10000)]
+    /// `send_and_wait` resolves after the action’s synchronous follow-ups, like `sync`.
+    fn test_send_and_wait_includes_follow_up_actions() {
+        let characters = Arc::new(Mutex::new(Default::default()));
+        let store = Store::with_initial(State {
+            characters: characters.clone(),
+        });
+
+        use Action::*;
+        let first = store.send_and_wait(External('1'));
+        let second = store.send_and_wait(External('2'));
+
+        futures::executor::block_on(async {
+            first.await.unwrap();
+            assert!(characters.lock().unwrap().len() >= 5); // '2' may also have been processed
+
+            second.await.unwrap();
+            assert_eq!(characters.lock().unwrap().len(), 6);
+        });
+
+        store.into_inner().unwrap();
+    }
+
+    mod panics {
+        use super::*;
+        use crate::{SendError, StoreError};
+
+        #[derive(Clone, Debug, Default, PartialEq)]
+        pub struct State(usize);
+
+        #[derive(Clone, Debug, PartialEq)]
+        pub enum Action {
+            Add(usize),
+            Panic,
+        }
+
+        impl Reducer for State {
+            type Action = Action;
+            type Output = usize;
+
+            fn reduce(&mut self, action: Action, send: impl Effects<Action>) {
+                match action {
+                    Action::Add(n) => self.0 += n,
+                    Action::Panic => {
+                        self.0 += 100;
+                        send.action(Action::Add(1000)); // dropped along with the action
+                        panic!("boom");
+                    }
+                }
+            }
+        }
+
+        impl From<State> for usize {
+            fn from(value: State) -> Self {
+                value.0
+            }
+        }
+
+        #[test]
+        #[cfg(not(miri))]
+        #[timeout(10000)]
+        fn test_panic_stops_the_store() {
+            let store = Store::with_initial(State::default());
+            assert!(store.is_alive());
+
+            store.send(Action::Add(1));
+            // returns, rather than waiting forever
+            assert_eq!(store.sync(Action::Panic), Err(SendError::Panicked));
+
+            assert!(!store.is_alive());
+            assert_eq!(
+                store.try_send(Action::Add(1)),
+                Err(SendError::Disconnected(Action::Add(1)))
+            );
+            assert_eq!(
+                store.sync(Action::Add(1)),
+                Err(SendError::Disconnected(Action::Add(1)))
+            );
+
+            assert_eq!(
+                store.into_inner(),
+                Err(StoreError::Panicked(String::from("boom")))
+            );
+        }
+
+        #[test]
+        #[cfg(not(miri))]
+        #[timeout(10000)]
+        fn test_panic_skips_the_action() {
+            let store = Store::with_initial(State::default()).on_panic(PanicPolicy::skip());
+
+            store.send(Action::Add(1));
+            store.send(Action::Panic);
+            store.sync(Action::Add(2)).unwrap();
+
+            assert!(store.is_alive());
+            assert_eq!(store.into_inner(), Ok(3));
+        }
+
+        #[test]
+        #[cfg(not(miri))]
+        #[timeout(10000)]
+        fn test_panic_restarts_the_store() {
+            let store =
+                Store::with_initial(State::default()).on_panic(PanicPolicy::restart(|| State(10)));
+
+            store.send(Action::Add(1));
+            store.send(Action::Panic);
+            store.send(Action::Add(2));
+
+            assert_eq!(store.into_inner(), Ok(12));
+        }
+
+        #[test]
+        #[cfg(not(miri))]
+        #[timeout(10000)]
+        fn test_send_and_wait_on_a_stopped_store() {
+            use futures::executor::block_on;
+
+            let store = Store::with_initial(State::default());
+
+            store.send(Action::Panic);
+            let queued = store.send_and_wait(Action::Add(1)); // dropped as the store stops
+            assert_eq!(
+                block_on(queued),
+                Err(SendError::Disconnected(Action::Add(1)))
+            );
+
+            assert!(!store.is_alive());
+            assert_eq!(
+                block_on(store.send_and_wait(Action::Add(2))),
+                Err(SendError::Disconnected(Action::Add(2)))
+            );
+        }
+
+        #[test]
+        #[cfg(not(miri))]
+        #[timeout(10000)]
+        fn test_with_state_on_a_stopped_store() {
+            use futures::executor::block_on;
+
+            let store = Store::with_initial(State::default());
+
+            store.send(Action::Panic);
+            let queued = store.with_state_async(|state| state.0); // dropped as the store stops
+            assert_eq!(block_on(queued), Err(SendError::Disconnected(())));
+
+            assert!(!store.is_alive());
+            assert_eq!(
+                store.with_state(|state| state.0),
+                Err(SendError::Disconnected(()))
+            );
+        }
+    }
+
+    mod timers {
+        use std::time::Duration;
+
+        use super::*;
+        use crate::effects::scheduler::Reactor;
+        use crate::Resume;
+
+        #[derive(Default)]
+        pub struct State(usize);
+
+        #[derive(Clone, Debug, PartialEq)]
+        pub enum Action {
+            Start(Duration),
+            Tick,
+        }
+
+        impl Reducer for State {
+            type Action = Action;
+            type Output = usize;
+
+            fn reduce(&mut self, action: Action, send: impl Effects<Action>) {
+                match action {
+                    Action::Start(duration) => send.after(duration, Action::Tick).detach(),
+                    Action::Tick => self.0 += 1,
+                }
+            }
+        }
+
+        impl From<State> for usize {
+            fn from(value: State) -> Self {
+                value.0
+            }
+        }
+
+        #[test]
+        #[cfg(not(miri))]
+        #[timeout(10000)]
+        fn test_paused_timers_wait_for_resume() {
+            let store = Store::with_dependency(State::default, Reactor::default);
+
+            store.send(Action::Start(Duration::from_millis(20)));
+            store.send(Action::Start(Duration::from_millis(20)));
+            store.pause_timers();
+
+            std::thread::sleep(Duration::from_millis(100));
+            assert_eq!(store.with_state(|state| state.0), Ok(0));
+
+            store.resume_timers(Resume::Immediately);
+            while store.with_state(|state| state.0).unwrap() < 2 {
+                std::thread::yield_now();
+            }
+
+            store.send(Action::Start(Duration::from_millis(20)));
+            store.pause_timers();
+            store.resume_timers(Resume::Preserving);
+            while store.with_state(|state| state.0).unwrap() < 3 {
+                std::thread::yield_now();
+            }
+
+            assert_eq!(store.into_inner(), Ok(3));
+        }
+    }
+
     #[test]
     #[cfg(not(miri))]
     #[timeout(10000)]
@@ -210,6 +933,6 @@ pub mod tests {
         }
 
         let store = Store::<State>::default();
-        store.into_inner();
+        store.into_inner().unwrap();
     }
 }
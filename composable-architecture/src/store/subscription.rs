@@ -0,0 +1,42 @@
+//! A stream of values projected from a running `Store`’s state.
+//!
+//! Subscribers live on the runtime thread and are notified after every action received by the
+//! [`Store`](crate::Store) has been processed. Each subscriber compares the newly projected value
+//! against the last one it emitted, so unchanged values never cross the thread boundary.
+
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::channel::mpsc::UnboundedReceiver;
+use futures::{Stream, StreamExt};
+
+/// A [`Stream`] of projected state values.
+///
+/// This `struct` is created by the [`subscribe`] method on [`Store`]. See its documentation for more.
+///
+/// [`subscribe`]: crate::Store::subscribe
+/// [`Store`]: crate::Store
+#[must_use = "streams do nothing unless polled"]
+pub struct Subscription<T> {
+    receiver: UnboundedReceiver<T>,
+}
+
+impl<T> Subscription<T> {
+    pub(crate) fn new(receiver: UnboundedReceiver<T>) -> Self {
+        Self { receiver }
+    }
+}
+
+impl<T> Stream for Subscription<T> {
+    type Item = T;
+
+    #[inline(always)]
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_next_unpin(cx)
+    }
+
+    #[inline(always)]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.receiver.size_hint()
+    }
+}
@@ -6,11 +6,11 @@ use std::time::Duration;
 /// `advance` moves a simulated clock used by the test scheduler—it does **not** sleep.
 /// This makes scheduled effects deterministic and fast.
 ///
-/// This [`debounce`] example exercises scheduling and task cancellation; all
+/// This [`debounce_id`] example exercises scheduling and task cancellation; all
 /// with deterministic control of over the (simulated) passage of time.
 ///
 /// [`TestStore`]: `crate::TestStore`
-/// [`debounce`]: `crate::effects::Scheduler::debounce`
+/// [`debounce_id`]: `crate::effects::Effects::debounce_id`
 ///
 /// [tasks]: `crate::effects::Scheduler`
 /// [futures]: `crate::effects::Effects::future`
@@ -20,26 +20,11 @@ use std::time::Duration;
 /// # use std::time::Duration;
 /// # use composable::*;
 /// #
-/// #[derive(Debug, Default)]
-///  struct State {
-///      previous: Option<Task>,
-///      n: usize,
-///  }
+/// #[derive(Clone, Debug, Default, PartialEq)]
+/// struct State {
+///     n: usize,
+/// }
 ///
-/// # impl PartialEq for State {
-/// #     fn eq(&self, other: &Self) -> bool {
-/// #         self.n.eq(&other.n)
-/// #     }
-/// # }
-/// #
-/// # impl Clone for State {
-/// #     fn clone(&self) -> Self {
-/// #         Self {
-/// #             previous: None,
-/// #             n: self.n
-/// #         }
-/// #     }
-/// # }
 /// #[derive(Clone, Debug, PartialEq)]
 /// enum Action {
 ///     Send,
@@ -55,9 +40,9 @@ use std::time::Duration;
 ///     fn reduce(&mut self, action: Action, send: impl Effects<Action>) {
 ///         match action {
 ///             Send => {
-///                 send.debounce(
+///                 send.debounce_id(
+///                     "debounce",
 ///                     Recv,
-///                     &mut self.previous,
 ///                     Interval::Trailing(Duration::from_secs(4)),
 ///                 );
 ///             }
@@ -6,44 +6,72 @@
 //! Key behaviours:
 //!
 //! - Reducer effects are queued, not automatically drained. Tests must explicitly `recv` them.
-//! - The harness is strict: leaving queued actions unhandled fails the test (including on `Drop`).
+//! - The harness is strict: leaving queued actions unhandled fails the test (including on `Drop`);
+//!   unless its [`Exhaustivity`] is relaxed.
 //! - Scheduled work can be driven deterministically via [`TestClock::advance`].
 //! - Asynchronous work running on the local executor can be drained via [`TestStore::wait`], but
 //!   beware infinite streams.
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::VecDeque;
-use std::fmt::Debug;
+use std::fmt::{Arguments, Debug};
+use std::mem::take;
+use std::panic::Location;
 use std::rc::Rc;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
 
-use futures::executor::{LocalPool, LocalSpawner};
+use futures::executor::LocalPool;
 use futures::stream::iter;
-use futures::task::LocalSpawnExt;
-use futures::{pin_mut, Stream, StreamExt};
+use futures::task::{LocalSpawn, LocalSpawnExt};
+use futures::{Stream, StreamExt};
 
 pub use clock::TestClock;
 
 use crate::dependencies::{guard::Guard, Dependency};
-use crate::effects::{scheduler::Reactor, Delay, Effects, Scheduler};
+use crate::effects::{
+    cancellation::{sweep, Cancellations},
+    scheduler::Reactor,
+    Delay, Effects, Executor, Resume, Running, Scheduler, Workers,
+};
 use crate::reducer::Reducer;
+use crate::store::middleware::{Chain, Middleware};
+use crate::store::recording::{Origin, Recording};
 use crate::Task;
 
 mod clock;
 
+/// How strictly a [`TestStore`] checks the reducer’s behaviour; see
+/// [`TestStore::set_exhaustivity`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Exhaustivity {
+    /// Every state change and every received action must be asserted. This is the default.
+    #[default]
+    On,
+    /// Only the state fields, and the received actions, that a test asserts are checked; as with
+    /// `Off`. But a warning is printed for every received action that is skipped, rather than
+    /// asserted.
+    Partial,
+    /// Only the state fields, and the received actions, that a test asserts are checked.
+    Off,
+}
+
 #[doc = include_str!("README.md")]
-pub struct TestStore<State: Reducer>
+pub struct TestStore<State: Reducer, E: Executor = LocalPool>
 where
     <State as Reducer>::Action: Debug,
 {
     /// The current reducer state. Stored as `Option` so we can move it out in `into_inner` without
     /// violating `Drop` invariants.
     state: Option<State>, // `Option` so that `into_inner` does not break `Drop`
-    pool: LocalPool,
+    executor: E,
+    middleware: Chain<State>,
+    exhaustivity: Exhaustivity,
 
     // external polling
     inner: Rc<RefCell<Inner<<State as Reducer>::Action>>>,
     reactor: Guard<Reactor>,
+    cancellations: Guard<Cancellations>,
+    workers: Guard<Workers>,
 }
 
 impl<State: Reducer> Default for TestStore<State>
@@ -56,12 +84,21 @@ where
     }
 }
 
-impl<State: Reducer> Drop for TestStore<State>
+impl<State: Reducer, E: Executor> Drop for TestStore<State, E>
 where
     <State as Reducer>::Action: Debug,
 {
     #[track_caller]
     fn drop(&mut self) {
+        if self.exhaustivity != Exhaustivity::On {
+            let unreceived = take(&mut self.inner.borrow_mut().actions);
+            if !unreceived.is_empty() {
+                self.warn(format_args!("actions were not received: {unreceived:#?}"));
+            }
+
+            return;
+        }
+
         // Strict by design:
         // if actions were emitted and not asserted (via `recv`), the test should fail.
         //
@@ -78,7 +115,7 @@ where
     }
 }
 
-impl<State: Reducer> TestClock for TestStore<State>
+impl<State: Reducer, E: Executor> TestClock for TestStore<State, E>
 where
     <State as Reducer>::Action: Debug,
 {
@@ -89,14 +126,14 @@ where
         drop(inner);
 
         let timer = Dependency::<Reactor>::get();
+        timer.advance(now);
 
         // Drive the local executor and poll the test scheduler until no further progress can be made.
         // This deterministically advances delayed work without sleeping.
         loop {
-            self.pool.run_until_stalled();
-            timer.poll(now);
+            self.executor.run_until_stalled();
 
-            if !self.pool.try_run_one() {
+            if !timer.poll(now) {
                 break;
             }
         }
@@ -117,45 +154,148 @@ where
 
     /// Creates a new `Store` with `state` as its initial state.
     pub fn with_initial(state: State) -> Self {
-        let pool = LocalPool::new();
-        let spawner = pool.spawner();
+        Self::with_executor(state, LocalPool::new())
+    }
+}
+
+impl<State: Reducer, E: Executor> TestStore<State, E>
+where
+    <State as Reducer>::Action: Debug,
+{
+    /// Creates a new `Store` with `state` as its initial state, whose effects run on `executor`.
+    ///
+    /// See [`Store::with_executor`][`crate::Store::with_executor`].
+    pub fn with_executor(state: State, executor: E) -> Self {
+        let spawner = executor.spawner();
+        let reactor = Reactor::new();
 
         Self {
             state: Some(state),
-            inner: Inner::new(spawner),
-            reactor: Guard::new(Reactor::new()),
-            pool,
+            inner: Inner::new(spawner, reactor.now()),
+            reactor: Guard::new(reactor),
+            cancellations: Guard::new(Cancellations::default()),
+            workers: Guard::new(Workers::inline()),
+            middleware: Default::default(),
+            exhaustivity: Exhaustivity::On,
+            executor,
+        }
+    }
+
+    /// Sets how strictly the `TestStore` checks the reducer’s behaviour.
+    ///
+    /// When it is not [`Exhaustivity::On`]:
+    ///
+    /// - The `assert` closures of [`send`][`TestStore::send`] and [`recv`][`TestStore::recv`] are
+    ///   given the state _after_ the action, rather than before it, and should set the fields
+    ///   being checked to their expected values. Other fields are left unchecked.
+    /// - [`send`][`TestStore::send`] first [skips][`TestStore::skip_received_actions`] any actions
+    ///   that have been received, rather than failing.
+    /// - [`recv`][`TestStore::recv`] skips received actions until it finds the expected one.
+    /// - Dropping the `TestStore` with actions yet to be received does not fail the test.
+    ///
+    /// Skipped actions are still reduced, so the state carries on changing as it would in a
+    /// [`Store`](crate::Store). [`Exhaustivity::Partial`] prints a warning as it skips them.
+    ///
+    /// This allows a large integration test, of a parent built with
+    /// [`RecursiveReducer`][`crate::derive_macros`] for example, to check only what it is
+    /// concerned with.
+    pub fn set_exhaustivity(&mut self, exhaustivity: Exhaustivity) {
+        self.exhaustivity = exhaustivity;
+    }
+
+    /// Reduces every action that has been received without asserting anything about them, or the
+    /// state changes they make; including any actions they send in turn.
+    pub fn skip_received_actions(&mut self)
+    where
+        <State as Reducer>::Action: 'static,
+    {
+        loop {
+            let Some(action) = self.inner.borrow_mut().actions.pop_front() else {
+                break;
+            };
+
+            self.middleware
+                .reduce(self.state.as_mut().unwrap(), action, self.inner.clone());
         }
     }
+
+    /// Cancels every effect that is still running, so that it does not send any more actions.
+    ///
+    /// Actions that they have already sent must still be received, or skipped.
+    pub fn skip_in_flight_effects(&mut self) {
+        let mut inner = self.inner.borrow_mut();
+        let tasks = take(&mut inner.tasks);
+        inner.swept = 0;
+        drop(inner);
+
+        for task in tasks.iter().filter(|task| task.is_running()) {
+            task.abort();
+        }
+
+        self.executor.run_until_stalled(); // dropping the cancelled effects
+    }
+
+    /// Sets the simulated wall-clock to `time`, as if the system clock had been changed.
+    ///
+    /// The simulated clock used by [`advance`][`TestClock::advance`] does not move. Delays until a
+    /// wall-clock time notice the change the next time they check the clock; within a second.
+    pub fn set_system_time(&mut self, time: SystemTime) {
+        Dependency::<Reactor>::get().set_system_time(time);
+    }
+
+    /// Pauses every timer of the `TestStore`’s effects, as [`Store::pause_timers`] does.
+    ///
+    /// [`advance`][`TestClock::advance`] still moves time forwards, but no timers fire.
+    ///
+    /// [`Store::pause_timers`]: crate::Store::pause_timers
+    pub fn pause_timers(&mut self) {
+        Dependency::<Reactor>::get().pause();
+    }
+
+    /// Resumes the timers paused by [`pause_timers`][`TestStore::pause_timers`].
+    ///
+    /// Timers that fire immediately do so on the next [`advance`][`TestClock::advance`].
+    pub fn resume_timers(&mut self, resume: Resume) {
+        Dependency::<Reactor>::get().resume(resume);
+    }
+
+    /// Adds `middleware` to the end of the `TestStore`’s [`Middleware`] chain.
+    ///
+    /// Middleware runs whenever the reducer does; during both [`send`][`TestStore::send`] and
+    /// [`recv`][`TestStore::recv`].
+    pub fn add_middleware(&mut self, middleware: impl Middleware<State> + 'static) {
+        self.middleware.push(Box::new(middleware));
+    }
+
     /// Calls the `Store`’s [`Reducer`][`crate::Reducer`] with `action` and asserts the
     /// expected state changes.
     ///
     /// # Panics
-    /// Panics if there is an unhandled queued action (use `recv` first).
+    /// Panics if there is an unhandled queued action (use `recv` first); unless the
+    /// [`Exhaustivity`] is relaxed.
     #[track_caller]
     pub fn send(&mut self, action: <State as Reducer>::Action, assert: impl FnOnce(&mut State))
     where
         State: Clone + Debug + PartialEq,
         <State as Reducer>::Action: 'static,
     {
-        let mut expected = self.state.clone();
-        assert(expected.as_mut().unwrap());
-
-        assert!(
-            self.inner.borrow().actions.is_empty(),
-            "an extra action was received: {:#?}",
-            self.inner
-                .borrow_mut()
-                .actions
-                .drain(..)
-                .collect::<Vec<_>>()
-        );
+        if self.exhaustivity == Exhaustivity::On {
+            assert!(
+                self.inner.borrow().actions.is_empty(),
+                "an extra action was received: {:#?}",
+                self.inner
+                    .borrow_mut()
+                    .actions
+                    .drain(..)
+                    .collect::<Vec<_>>()
+            );
+        } else if !self.inner.borrow().actions.is_empty() {
+            let skipped = format!("{:#?}", self.inner.borrow().actions);
+            self.warn(format_args!("skipping received actions: {skipped}"));
+            self.skip_received_actions();
+        }
 
-        self.state
-            .as_mut()
-            .unwrap()
-            .reduce(action, self.inner.clone());
-        assert_eq!(self.state, expected);
+        self.reduce(action, assert);
     }
 
     /// Checks that the `Store`’s [`Reducer`][`crate::Reducer`] was called with `action`
@@ -163,30 +303,110 @@ where
     ///
     /// # Panics
     /// Panics if no action is queued, or if the next queued action does not equal `action`.
+    /// Unless the [`Exhaustivity`] is relaxed, when the actions before `action` are skipped.
     #[track_caller]
     pub fn recv(&mut self, action: <State as Reducer>::Action, assert: impl FnOnce(&mut State))
     where
         State: Clone + Debug + PartialEq,
         <State as Reducer>::Action: Debug + PartialEq + 'static,
     {
-        let mut expected = self.state.clone();
-        assert(expected.as_mut().unwrap());
-
-        let received = self
-            .inner
-            .borrow_mut()
-            .actions
-            .pop_front()
-            .expect("no action received");
-        assert_eq!(received, action);
-
-        self.state
-            .as_mut()
-            .unwrap()
-            .reduce(action, self.inner.clone());
+        loop {
+            let received = self.inner.borrow_mut().actions.pop_front();
+            let received = match (received, self.exhaustivity) {
+                (None, Exhaustivity::On) => panic!("no action received"),
+                (None, _) => panic!("no matching action received: {action:#?}"),
+                (Some(received), Exhaustivity::On) => {
+                    assert_eq!(received, action);
+                    break;
+                }
+                (Some(received), _) if received == action => break,
+                (Some(received), _) => received,
+            };
+
+            self.warn(format_args!("skipping received action: {received:#?}"));
+            self.middleware
+                .reduce(self.state.as_mut().unwrap(), received, self.inner.clone());
+        }
+
+        self.reduce(action, assert);
+    }
+
+    /// Reduces `action` and asserts the state changes made, according to the [`Exhaustivity`].
+    #[track_caller]
+    fn reduce(&mut self, action: <State as Reducer>::Action, assert: impl FnOnce(&mut State))
+    where
+        State: Clone + Debug + PartialEq,
+        <State as Reducer>::Action: 'static,
+    {
+        let expected = if self.exhaustivity == Exhaustivity::On {
+            let mut expected = self.state.clone();
+            assert(expected.as_mut().unwrap());
+
+            self.middleware
+                .reduce(self.state.as_mut().unwrap(), action, self.inner.clone());
+            expected
+        } else {
+            self.middleware
+                .reduce(self.state.as_mut().unwrap(), action, self.inner.clone());
+
+            // only the fields that `assert` sets are checked
+            let mut expected = self.state.clone();
+            assert(expected.as_mut().unwrap());
+            expected
+        };
+
         assert_eq!(self.state, expected);
     }
 
+    /// Prints a warning about something that was skipped, if the [`Exhaustivity`] is `Partial`.
+    #[track_caller]
+    fn warn(&self, message: Arguments<'_>) {
+        if self.exhaustivity == Exhaustivity::Partial {
+            eprintln!("warning: {message}\n  at {}", Location::caller());
+        }
+    }
+
+    /// Replays a [`Recording`] made by a live [`Store`](crate::Store).
+    ///
+    /// The simulated clock is [advanced][`TestClock::advance`] to the time of each entry before it
+    /// is replayed:
+    ///
+    /// - [external][`Origin::External`] actions are sent to the reducer, as with
+    ///   [`send`][`TestStore::send`], but without asserting any state changes.
+    /// - [effect][`Origin::Effect`] actions must have been emitted again by the reducer’s effects,
+    ///   as with [`recv`][`TestStore::recv`].
+    ///
+    /// # Panics
+    /// Panics if the reducer’s effects do not reproduce the recorded effect actions in order.
+    #[track_caller]
+    pub fn replay(&mut self, recording: &Recording<<State as Reducer>::Action>)
+    where
+        <State as Reducer>::Action: Clone + PartialEq + 'static,
+    {
+        let mut elapsed = Duration::ZERO;
+
+        for entry in recording {
+            self.advance(entry.at.saturating_sub(elapsed));
+            elapsed = elapsed.max(entry.at);
+
+            let action = entry.action.clone();
+            match entry.origin {
+                Origin::External => assert!(
+                    self.inner.borrow().actions.is_empty(),
+                    "an extra action was received before replaying {action:#?}: {:#?}",
+                    self.inner.borrow().actions,
+                ),
+                Origin::Effect => {
+                    let received = self.inner.borrow_mut().actions.pop_front();
+                    assert_eq!(received.as_ref(), Some(&action), "the replay has diverged");
+                }
+            }
+
+            self.middleware
+                .reduce(self.state.as_mut().unwrap(), action, self.inner.clone());
+        }
+    }
+
     /// Waits until all scheduled tasks have completed.
     ///
     /// This runs the underlying local executor until it is idle. It is useful for reducers that
@@ -208,7 +428,7 @@ where
     /// [timeout]: https://docs.rs/ntest/latest/ntest/attr.timeout.html
     /// [max_time]: https://docs.rs/divan/0.1.14/divan/attr.bench.html#max_time
     pub fn wait(&mut self) {
-        self.pool.run()
+        self.executor.run()
     }
 
     /// Consumes the `Store` and returns its current `state` value.
@@ -222,7 +442,11 @@ where
 
 struct Inner<Action> {
     actions: VecDeque<Action>,
-    spawner: LocalSpawner,
+    /// Observers of the effects’ tasks, so that they can be skipped.
+    tasks: Vec<Task>,
+    /// The number of tasks when finished tasks were last swept out.
+    swept: usize,
+    spawner: Rc<dyn LocalSpawn>,
     now: Instant,
 }
 
@@ -241,16 +465,23 @@ where
         let effects = self.clone();
         let spawner = self.borrow().spawner.clone();
 
-        let handle = spawner
-            .spawn_local_with_handle(async move {
-                pin_mut!(stream);
-                while let Some(action) = stream.next().await {
-                    effects.action(action);
-                }
-            })
+        let (running, mut task) = Running::new();
+
+        task.handle = spawner
+            .spawn_local_with_handle(running.run(
+                stream,
+                move |action| effects.action(action),
+                || false,
+            ))
             .ok();
 
-        Task { handle, when: None }
+        let inner = &mut *self.borrow_mut();
+        sweep(&mut inner.tasks, Cell::from_mut(&mut inner.swept), |task| {
+            task
+        });
+        inner.tasks.push(task.observer());
+
+        task
     }
 }
 
@@ -285,11 +516,136 @@ where
 }
 
 impl<Action> Inner<Action> {
-    fn new(spawner: LocalSpawner) -> Rc<RefCell<Self>> {
+    fn new(spawner: Rc<dyn LocalSpawn>, now: Instant) -> Rc<RefCell<Self>> {
         Rc::new(RefCell::new(Self {
             actions: Default::default(),
-            now: Instant::now(),
+            tasks: Default::default(),
+            swept: 0,
+            now,
             spawner,
         }))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::future::ready;
+
+    use crate::effects::Interval;
+    use crate::{Effects, Exhaustivity, Reducer, TestClock, TestStore};
+
+    #[derive(Clone, Debug, Default, PartialEq)]
+    struct State {
+        count: usize,
+        log: Vec<&'static str>,
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Action {
+        Start,
+        Logged,
+        Counted,
+        Tick,
+        Every,
+        Once,
+    }
+
+    impl Reducer for State {
+        type Action = Action;
+        type Output = Self;
+
+        fn reduce(&mut self, action: Action, send: impl Effects<Action>) {
+            match action {
+                Action::Start => {
+                    self.log.push("start");
+                    send.action(Action::Logged);
+                    send.action(Action::Counted);
+                }
+                Action::Logged => self.log.push("logged"),
+                Action::Counted | Action::Tick => self.count += 1,
+                Action::Every => send
+                    .every(Interval::Trailing(Duration::from_secs(1)), Action::Tick)
+                    .detach(),
+                Action::Once => send.future(ready(Some(Action::Tick))),
+            }
+        }
+    }
+
+    #[test]
+    fn test_only_asserted_fields_are_checked() {
+        let mut store = TestStore::<State>::default();
+        store.set_exhaustivity(Exhaustivity::Off);
+
+        store.send(Action::Start, |state| state.log = vec!["start"]);
+        store.recv(Action::Counted, |state| state.count = 1); // skipping `Logged`
+
+        assert_eq!(store.into_inner().log, vec!["start", "logged"]);
+    }
+
+    #[test]
+    fn test_send_skips_received_actions() {
+        let mut store = TestStore::<State>::default();
+        store.set_exhaustivity(Exhaustivity::Partial);
+
+        store.send(Action::Start, |_| {});
+        store.send(Action::Start, |state| state.count = 1); // skipped actions are still reduced
+        store.skip_received_actions();
+
+        assert_eq!(store.into_inner().count, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "an extra action was received")]
+    fn test_exhaustive_send_fails_on_received_actions() {
+        let mut store = TestStore::<State>::default();
+
+        store.send(Action::Start, |state| state.log = vec!["start"]);
+        store.send(Action::Start, |_| {});
+    }
+
+    #[test]
+    #[should_panic(expected = "no matching action received")]
+    fn test_recv_fails_without_a_matching_action() {
+        let mut store = TestStore::<State>::default();
+        store.set_exhaustivity(Exhaustivity::Off);
+
+        store.send(Action::Start, |_| {});
+        store.recv(Action::Tick, |_| {});
+    }
+
+    #[test]
+    fn test_unreceived_actions_are_allowed_on_drop() {
+        let mut store = TestStore::<State>::default();
+        store.set_exhaustivity(Exhaustivity::Off);
+
+        store.send(Action::Start, |_| {});
+    }
+
+    #[test]
+    fn test_in_flight_effects_can_be_skipped() {
+        let mut store = TestStore::<State>::default();
+
+        store.send(Action::Every, |_| {});
+        store.advance(Duration::from_secs(1));
+        store.recv(Action::Tick, |state| state.count = 1);
+
+        store.skip_in_flight_effects();
+        store.advance(Duration::from_secs(10)); // no more ticks
+    }
+
+    #[test]
+    fn test_finished_tasks_are_swept_out() {
+        let mut store = TestStore::<State>::default();
+
+        for n in 1..=1000 {
+            store.send(Action::Once, |_| {});
+            store.advance(Duration::ZERO);
+            store.recv(Action::Tick, |state| state.count = n);
+        }
+
+        let tasks = store.inner.borrow().tasks.len();
+        assert!(tasks <= 65, "{tasks} tasks are still observed");
+    }
+}
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::time::Duration;
 
 use composable::*;
 
@@ -20,6 +21,7 @@ struct ChildState {
 #[derive(Clone, Debug, PartialEq)]
 enum ChildAction {
     EmitPing,
+    PingEverySecond,
     Ping,
 }
 
@@ -36,6 +38,10 @@ impl Reducer for ChildState {
                 // This lets us prove the "effect is queued" behaviour in TestStore.
                 send.action(Ping);
             }
+            PingEverySecond => {
+                send.every(Interval::Trailing(Duration::from_secs(1)), Ping)
+                    .detach();
+            }
             Ping => {
                 self.log.push("ping");
             }
@@ -153,3 +159,76 @@ fn multiple_keyed_fields_work_when_keys_are_distinct_types() {
     assert_eq!(state.by_id.get(&Id(1)).unwrap().log, vec!["ping"]);
     assert_eq!(state.by_name.get(&Name("A")).unwrap().log, vec!["ping"]);
 }
+
+#[test]
+/// Tasks a child starts through `scope_keyed` are cancelled along with the child; whether it is
+/// removed with `KeyedState::remove`, or in some other way and then sent an action.
+fn removing_a_child_cancels_its_tasks() {
+    #[derive(Clone, Debug, Default, PartialEq, RecursiveReducer)]
+    struct State {
+        children: KeyedState<Id, ChildState>,
+    }
+
+    /// The key of a child to remove without `KeyedState::remove`; a distinct type from `Id`,
+    /// so that both actions can be converted `From` their payloads.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct Forgotten(Id);
+
+    #[derive(Clone, From, TryInto, Debug, PartialEq)]
+    enum Action {
+        Child(Keyed<Id, ChildAction>),
+        Remove(Id),
+        Forget(Forgotten),
+    }
+
+    impl RecursiveReducer for State {
+        type Action = Action;
+
+        fn reduce(&mut self, action: Action, _send: impl Effects<Action>) {
+            match action {
+                Action::Child(_) => {}
+                Action::Remove(id) => {
+                    self.children.remove(&id);
+                }
+                Action::Forget(Forgotten(id)) => {
+                    self.children.0.remove(&id);
+                }
+            }
+        }
+    }
+
+    let mut state = State::default();
+    state.children.insert(Id(1), ChildState::default());
+    state.children.insert(Id(2), ChildState::default());
+
+    let mut store = TestStore::with_initial(state);
+    let ping = |id| Action::Child(Keyed::new(Id(id), ChildAction::Ping));
+
+    store.send(
+        Action::Child(Keyed::new(Id(1), ChildAction::PingEverySecond)),
+        |_| {},
+    );
+    store.advance(Duration::ZERO);
+    store.advance(Duration::from_secs(1));
+    store.recv(ping(1), |state| {
+        state.children.get_mut(&Id(1)).unwrap().log = vec!["ping"];
+    });
+
+    store.send(Action::Remove(Id(1)), |state| {
+        state.children.0.remove(&Id(1));
+    });
+    store.advance(Duration::from_secs(5)); // no more pings
+
+    store.send(
+        Action::Child(Keyed::new(Id(2), ChildAction::PingEverySecond)),
+        |_| {},
+    );
+    store.advance(Duration::ZERO);
+    store.send(Action::Forget(Forgotten(Id(2))), |state| {
+        state.children.0.remove(&Id(2));
+    });
+
+    store.advance(Duration::from_secs(1));
+    store.recv(ping(2), |_| {}); // ignored, as the child is gone, but cancels its tasks
+    store.advance(Duration::from_secs(5));
+}